@@ -0,0 +1,478 @@
+//! Fast Fourier transform.
+
+use crate::{iq::IQ, math::Real, sample::Sample};
+
+/// Sample types that can be transformed by the FFT.
+///
+/// Real samples are transformed as complex samples with a zero imaginary part.
+pub trait FftSample: Sample {
+    fn to_iq(self) -> IQ;
+
+    /// Convert back from a complex sample, discarding the imaginary part if
+    /// this is a real sample type.
+    fn from_iq(value: IQ) -> Self;
+}
+
+impl FftSample for Real {
+    fn to_iq(self) -> IQ {
+        IQ::new(self, 0.0)
+    }
+
+    fn from_iq(value: IQ) -> Self {
+        value.i
+    }
+}
+
+impl FftSample for IQ {
+    fn to_iq(self) -> IQ {
+        self
+    }
+
+    fn from_iq(value: IQ) -> Self {
+        value
+    }
+}
+
+/// A precomputed plan for FFTs of a fixed size.
+///
+/// The forward transform is `X[k] = sum(x[n] * exp(-2j * PI * k * n / N))`,
+/// and the inverse transform is scaled by `1 / N` so that it exactly undoes
+/// the forward transform.
+///
+/// Power-of-two sizes use an iterative radix-2 algorithm. Other sizes use
+/// Bluestein's algorithm, which expresses the transform as a convolution
+/// that is computed with power-of-two FFTs at least twice as long, so they
+/// are several times slower than the nearest power of two and allocate a
+/// temporary buffer on each call.
+#[derive(Clone)]
+pub struct Fft {
+    algorithm: Algorithm,
+}
+
+#[derive(Clone)]
+enum Algorithm {
+    Radix2(Radix2),
+    Bluestein(Bluestein),
+}
+
+impl Fft {
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "FFT size must be nonzero");
+        let algorithm = if size.is_power_of_two() {
+            Algorithm::Radix2(Radix2::new(size))
+        } else {
+            Algorithm::Bluestein(Bluestein::new(size))
+        };
+        Self { algorithm }
+    }
+
+    pub fn size(&self) -> usize {
+        match &self.algorithm {
+            Algorithm::Radix2(radix2) => radix2.size(),
+            Algorithm::Bluestein(bluestein) => bluestein.chirp.len(),
+        }
+    }
+
+    /// Forward transform, in place.
+    pub fn forward(&self, buffer: &mut [IQ]) {
+        assert_eq!(buffer.len(), self.size());
+        match &self.algorithm {
+            Algorithm::Radix2(radix2) => radix2.transform(buffer, false),
+            Algorithm::Bluestein(bluestein) => bluestein.forward(buffer),
+        }
+    }
+
+    /// Inverse transform, in place.
+    pub fn inverse(&self, buffer: &mut [IQ]) {
+        assert_eq!(buffer.len(), self.size());
+        match &self.algorithm {
+            Algorithm::Radix2(radix2) => radix2.transform(buffer, true),
+            Algorithm::Bluestein(bluestein) => {
+                // IFFT(X) = conj(FFT(conj(X))) / N
+                for slot in buffer.iter_mut() {
+                    *slot = slot.conj();
+                }
+                bluestein.forward(buffer);
+                for slot in buffer.iter_mut() {
+                    *slot = slot.conj();
+                }
+            }
+        }
+        let scale = 1.0 / self.size() as Real;
+        for slot in buffer {
+            *slot *= scale;
+        }
+    }
+}
+
+/// `exp(-2j * PI * numerator / denominator)`, computed in double precision.
+fn twiddle(numerator: usize, denominator: usize) -> IQ {
+    let angle = -std::f64::consts::TAU * numerator as f64 / denominator as f64;
+    IQ::new(angle.cos() as Real, angle.sin() as Real)
+}
+
+/// Unscaled power-of-two FFT.
+#[derive(Clone)]
+struct Radix2 {
+    twiddles: Box<[IQ]>,
+    bit_reverse: Box<[usize]>,
+}
+
+impl Radix2 {
+    fn new(size: usize) -> Self {
+        let bits = size.trailing_zeros();
+
+        // Computed in double precision so the error doesn't accumulate in the
+        // later stages.
+        let twiddles = (0..size / 2).map(|k| twiddle(k, size)).collect();
+        let bit_reverse = (0..size)
+            .map(|i| {
+                if bits == 0 {
+                    0
+                } else {
+                    i.reverse_bits() >> (usize::BITS - bits)
+                }
+            })
+            .collect();
+
+        Self {
+            twiddles,
+            bit_reverse,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.bit_reverse.len()
+    }
+
+    /// Iterative radix-2 decimation-in-time transform.
+    fn transform(&self, buffer: &mut [IQ], inverse: bool) {
+        let size = self.size();
+        assert_eq!(buffer.len(), size);
+
+        for (i, &j) in self.bit_reverse.iter().enumerate() {
+            if i < j {
+                buffer.swap(i, j);
+            }
+        }
+
+        let mut half = 1;
+        while half < size {
+            let stride = size / (2 * half);
+            for block in buffer.chunks_exact_mut(2 * half) {
+                let (low, high) = block.split_at_mut(half);
+                for (k, (a, b)) in low.iter_mut().zip(high).enumerate() {
+                    let twiddle = self.twiddles[k * stride];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+                    let product = *b * twiddle;
+                    *b = *a - product;
+                    *a = *a + product;
+                }
+            }
+            half *= 2;
+        }
+    }
+}
+
+/// Unscaled forward FFT of any size, by Bluestein's (chirp-z) algorithm.
+///
+/// Using `k * n = (k^2 + n^2 - (k - n)^2) / 2`, the DFT becomes
+/// `X[k] = w[k] * sum(x[n] * w[n] * conj(w[k - n]))` with the chirp
+/// `w[n] = exp(-j * PI * n^2 / N)`, which is a convolution.
+#[derive(Clone)]
+struct Bluestein {
+    radix2: Radix2,
+    chirp: Box<[IQ]>,
+    /// Spectrum of the conjugated chirp, arranged for circular convolution
+    /// and including the `1 / M` scaling of the inverse transform.
+    kernel: Box<[IQ]>,
+}
+
+impl Bluestein {
+    fn new(size: usize) -> Self {
+        let padded = (2 * size - 1).next_power_of_two();
+        let radix2 = Radix2::new(padded);
+
+        // n^2 is reduced modulo 2N to keep the angle accurate for large n.
+        let chirp: Box<[IQ]> = (0..size)
+            .map(|n| twiddle((n * n) % (2 * size), 2 * size))
+            .collect();
+
+        let mut kernel = vec![IQ::ZERO; padded].into_boxed_slice();
+        kernel[0] = chirp[0].conj();
+        for n in 1..size {
+            kernel[n] = chirp[n].conj();
+            kernel[padded - n] = chirp[n].conj();
+        }
+        radix2.transform(&mut kernel, false);
+        let scale = 1.0 / padded as Real;
+        for slot in kernel.iter_mut() {
+            *slot *= scale;
+        }
+
+        Self {
+            radix2,
+            chirp,
+            kernel,
+        }
+    }
+
+    fn forward(&self, buffer: &mut [IQ]) {
+        let mut work = vec![IQ::ZERO; self.kernel.len()];
+        for ((slot, &sample), &chirp) in work.iter_mut().zip(&*buffer).zip(&self.chirp[..]) {
+            *slot = sample * chirp;
+        }
+        self.radix2.transform(&mut work, false);
+        for (slot, &kernel) in work.iter_mut().zip(&self.kernel[..]) {
+            *slot *= kernel;
+        }
+        self.radix2.transform(&mut work, true);
+        for ((slot, &value), &chirp) in buffer.iter_mut().zip(&work).zip(&self.chirp[..]) {
+            *slot = value * chirp;
+        }
+    }
+}
+
+/// A precomputed plan for FFTs of real input of a fixed size.
+///
+/// The spectrum of a real signal is conjugate-symmetric, so only the
+/// `size / 2 + 1` bins from zero to Nyquist are computed. For even sizes,
+/// the input is packed into a complex signal of half the length, which
+/// makes this about twice as fast as a complex FFT of the same size.
+#[derive(Clone)]
+pub struct RealFft {
+    size: usize,
+    half: Fft,
+    /// `exp(-2j * PI * k / size)` for `k` in `0..size / 2`, or empty if the
+    /// size is odd.
+    twiddles: Box<[IQ]>,
+}
+
+impl RealFft {
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "FFT size must be nonzero");
+        if size.is_multiple_of(2) {
+            Self {
+                size,
+                half: Fft::new(size / 2),
+                twiddles: (0..size / 2).map(|k| twiddle(k, size)).collect(),
+            }
+        } else {
+            Self {
+                size,
+                half: Fft::new(size),
+                twiddles: Box::new([]),
+            }
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of output bins, `size / 2 + 1`.
+    pub fn num_bins(&self) -> usize {
+        self.size / 2 + 1
+    }
+
+    /// Forward transform of `input`, writing bins zero to Nyquist to
+    /// `output`.
+    pub fn forward(&self, input: &[Real], output: &mut [IQ]) {
+        assert_eq!(input.len(), self.size);
+        assert_eq!(output.len(), self.num_bins());
+
+        if self.twiddles.is_empty() {
+            let mut work: Vec<IQ> = input.iter().map(|&x| IQ::new(x, 0.0)).collect();
+            self.half.forward(&mut work);
+            output.copy_from_slice(&work[..output.len()]);
+            return;
+        }
+
+        // Even samples in the real part, odd samples in the imaginary part.
+        let half = self.size / 2;
+        let mut work: Vec<IQ> = input
+            .chunks_exact(2)
+            .map(|pair| IQ::new(pair[0], pair[1]))
+            .collect();
+        self.half.forward(&mut work);
+
+        for (k, slot) in output.iter_mut().enumerate() {
+            let z = work[k % half];
+            let mirror = work[(half - k % half) % half].conj();
+            let even = (z + mirror) * 0.5;
+            // (z - mirror) / 2j
+            let diff = (z - mirror) * 0.5;
+            let odd = IQ::new(diff.q, -diff.i);
+            let twiddle = if k < half {
+                self.twiddles[k]
+            } else {
+                IQ::new(-1.0, 0.0)
+            };
+            *slot = even + odd * twiddle;
+        }
+    }
+
+    /// Inverse transform of bins zero to Nyquist in `input`, writing the
+    /// real signal to `output`.
+    ///
+    /// Only the real parts of the zero and Nyquist bins are used, since they
+    /// are real for any real signal.
+    pub fn inverse(&self, input: &[IQ], output: &mut [Real]) {
+        assert_eq!(input.len(), self.num_bins());
+        assert_eq!(output.len(), self.size);
+
+        if self.twiddles.is_empty() {
+            let mut work = vec![IQ::ZERO; self.size];
+            work[..input.len()].copy_from_slice(input);
+            for k in input.len()..self.size {
+                work[k] = input[self.size - k].conj();
+            }
+            work[0].q = 0.0;
+            self.half.inverse(&mut work);
+            for (slot, value) in output.iter_mut().zip(work) {
+                *slot = value.i;
+            }
+            return;
+        }
+
+        let half = self.size / 2;
+        let mut work: Vec<IQ> = (0..half)
+            .map(|k| {
+                let x = if k == 0 {
+                    IQ::new(input[0].i, 0.0)
+                } else {
+                    input[k]
+                };
+                let mirror = if k == 0 {
+                    IQ::new(input[half].i, 0.0)
+                } else {
+                    input[half - k].conj()
+                };
+                let even = (x + mirror) * 0.5;
+                let odd = (x - mirror) * 0.5 * self.twiddles[k].conj();
+                // even + j * odd
+                even + IQ::new(-odd.q, odd.i)
+            })
+            .collect();
+        self.half.inverse(&mut work);
+
+        for (pair, value) in output.chunks_exact_mut(2).zip(work) {
+            pair[0] = value.i;
+            pair[1] = value.q;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [usize; 9] = [1, 2, 3, 8, 12, 17, 64, 1000, 1024];
+
+    /// Relative error allowed against the direct DFT, which grows roughly
+    /// with the log of the size for both algorithms.
+    const TOLERANCE: Real = 64.0 * Real::EPSILON;
+
+    fn signal(len: usize) -> Vec<IQ> {
+        (0..len)
+            .map(|n| {
+                let hash = |k: usize| ((k * 7919 + 104729) % 2003) as Real / 1001.5 - 1.0;
+                IQ::new(hash(2 * n), hash(2 * n + 1))
+            })
+            .collect()
+    }
+
+    /// Direct evaluation of the DFT in double precision.
+    fn dft(input: &[IQ]) -> Vec<IQ> {
+        let len = input.len();
+        (0..len)
+            .map(|k| {
+                let (re, im) = input
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (n, x)| {
+                        let angle = -std::f64::consts::TAU * ((k * n) % len) as f64 / len as f64;
+                        let (sin, cos) = angle.sin_cos();
+                        let (i, q) = (x.i as f64, x.q as f64);
+                        (re + i * cos - q * sin, im + i * sin + q * cos)
+                    });
+                IQ::new(re as Real, im as Real)
+            })
+            .collect()
+    }
+
+    /// Largest difference between `actual` and `expected`, relative to the
+    /// largest value in `expected`.
+    fn relative_error(actual: &[IQ], expected: &[IQ]) -> Real {
+        assert_eq!(actual.len(), expected.len());
+        let scale = expected
+            .iter()
+            .fold(0.0, |max: Real, x| max.max(x.magnitude()));
+        let error = actual
+            .iter()
+            .zip(expected)
+            .fold(0.0, |max: Real, (&a, &b)| max.max((a - b).magnitude()));
+        error / scale
+    }
+
+    #[test]
+    fn forward_matches_dft() {
+        for size in SIZES {
+            let input = signal(size);
+            let mut output = input.clone();
+            Fft::new(size).forward(&mut output);
+            let error = relative_error(&output, &dft(&input));
+            let bound = TOLERANCE * (size as Real).log2().max(1.0);
+            assert!(error < bound, "size {size}: error {error}");
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        for size in SIZES {
+            let input = signal(size);
+            let fft = Fft::new(size);
+            let mut buffer = input.clone();
+            fft.forward(&mut buffer);
+            fft.inverse(&mut buffer);
+            let error = relative_error(&buffer, &input);
+            let bound = TOLERANCE * (size as Real).log2().max(1.0);
+            assert!(error < bound, "size {size}: error {error}");
+        }
+    }
+
+    #[test]
+    fn real_fft_matches_complex_fft() {
+        for size in SIZES {
+            let input: Vec<Real> = signal(size).iter().map(|x| x.i).collect();
+            let real_fft = RealFft::new(size);
+            let mut output = vec![IQ::ZERO; real_fft.num_bins()];
+            real_fft.forward(&input, &mut output);
+
+            let mut expected: Vec<IQ> = input.iter().map(|&x| x.to_iq()).collect();
+            Fft::new(size).forward(&mut expected);
+            expected.truncate(real_fft.num_bins());
+            let bound = TOLERANCE * (size as Real).log2().max(1.0);
+            let error = relative_error(&output, &expected);
+            assert!(error < bound, "size {size}: error {error}");
+
+            // DC and, for even sizes, Nyquist are real.
+            let last = output.len() - 1;
+            let scale = expected[0].magnitude().max(1.0);
+            assert!(
+                output[0].q.abs() < bound * scale,
+                "size {size}: DC {:?}",
+                output[0]
+            );
+            if size.is_multiple_of(2) {
+                assert!(output[last].q.abs() < bound * scale, "size {size}: Nyquist");
+            }
+
+            let mut round_trip = vec![0.0; size];
+            real_fft.inverse(&output, &mut round_trip);
+            for (n, (&x, &y)) in round_trip.iter().zip(&input).enumerate() {
+                assert!((x - y).abs() < bound, "size {size}: sample {n}: {x} != {y}");
+            }
+        }
+    }
+}
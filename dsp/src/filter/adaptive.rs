@@ -0,0 +1,510 @@
+//! Adaptive FIR filters (LMS, NLMS and RLS), and the automatic notch and
+//! line enhancer built on them.
+
+use std::ops;
+
+use crate::{filter::kernel::DelayLine, iq::IQ, math::Real, sample::Sample};
+
+/// Sample types that adaptive filters can operate on.
+///
+/// The filters are written for complex samples; for real samples, `conj` is
+/// the identity.
+pub trait AdaptiveSample: Sample + ops::Mul<Output = Self> {
+    fn conj(self) -> Self;
+
+    /// Real part.
+    fn re(self) -> Real;
+
+    /// Sample with the given real part and no imaginary part.
+    fn from_re(value: Real) -> Self;
+}
+
+impl AdaptiveSample for Real {
+    fn conj(self) -> Self {
+        self
+    }
+
+    fn re(self) -> Real {
+        self
+    }
+
+    fn from_re(value: Real) -> Self {
+        value
+    }
+}
+
+impl AdaptiveSample for IQ {
+    fn conj(self) -> Self {
+        IQ::conj(&self)
+    }
+
+    fn re(self) -> Real {
+        self.i
+    }
+
+    fn from_re(value: Real) -> Self {
+        IQ::new(value, 0.0)
+    }
+}
+
+/// An FIR filter whose weights adapt to minimize the error between its output
+/// and a desired signal.
+///
+/// The output is `y = sum(conj(weights[k]) * input[n - k])`.
+pub trait AdaptiveFilter<T: AdaptiveSample> {
+    /// Push an input sample and return the filter output, without adapting.
+    fn estimate(&mut self, input: T) -> T;
+
+    /// Adapt the weights given the error (desired minus output) of the last
+    /// call to [`estimate`](Self::estimate).
+    fn adapt(&mut self, error: T);
+
+    fn weights(&self) -> &[T];
+
+    /// Filter `input`, then adapt towards `desired`, returning the output and
+    /// the error.
+    fn process_sample(&mut self, input: T, desired: T) -> (T, T) {
+        let output = self.estimate(input);
+        let error = desired - output;
+        self.adapt(error);
+        (output, error)
+    }
+}
+
+fn estimate<T: AdaptiveSample>(weights: &[T], inputs: &[T]) -> T {
+    weights
+        .iter()
+        .zip(inputs)
+        .map(|(&weight, &input)| weight.conj() * input)
+        .sum()
+}
+
+/// Least mean squares adaptive filter.
+///
+/// Each weight takes a step of `step_size * input * conj(error)` down the
+/// error gradient. The step size must be small compared to `1 / (num_taps *
+/// input power)` to stay stable; see [`Nlms`] for a step size that doesn't
+/// depend on the input level.
+#[derive(Clone)]
+pub struct Lms<T = Real> {
+    weights: Box<[T]>,
+    inputs: DelayLine<T>,
+    step_size: Real,
+    leakage: Real,
+}
+
+impl<T: AdaptiveSample> Lms<T> {
+    /// Construct a filter with all weights zero.
+    ///
+    /// Each update scales the weights by `1 - leakage` before stepping, which
+    /// keeps them bounded (and slowly forgets them) when the input doesn't
+    /// excite every mode of the filter; zero means no leakage.
+    pub fn new(num_taps: usize, step_size: Real, leakage: Real) -> Self {
+        assert!(num_taps > 0);
+        Self {
+            weights: vec![T::ZERO; num_taps].into_boxed_slice(),
+            inputs: DelayLine::new(num_taps),
+            step_size,
+            leakage,
+        }
+    }
+
+    pub fn set_step_size(&mut self, step_size: Real) {
+        self.step_size = step_size;
+    }
+
+    pub fn set_leakage(&mut self, leakage: Real) {
+        self.leakage = leakage;
+    }
+}
+
+impl<T: AdaptiveSample> AdaptiveFilter<T> for Lms<T> {
+    fn estimate(&mut self, input: T) -> T {
+        self.inputs.push(input);
+        estimate(&self.weights, self.inputs.window())
+    }
+
+    fn adapt(&mut self, error: T) {
+        let step = error.conj() * self.step_size;
+        update(&mut self.weights, self.inputs.window(), step, self.leakage);
+    }
+
+    fn weights(&self) -> &[T] {
+        &self.weights
+    }
+}
+
+fn update<T: AdaptiveSample>(weights: &mut [T], inputs: &[T], step: T, leakage: Real) {
+    for (weight, &input) in weights.iter_mut().zip(inputs) {
+        *weight = *weight * (1.0 - leakage) + input * step;
+    }
+}
+
+/// Normalized least mean squares adaptive filter.
+///
+/// Like [`Lms`], but the step is divided by the energy of the inputs in the
+/// filter, so the convergence rate doesn't depend on the input level. It is
+/// stable for step sizes between 0 and 2, and a step size of 1 is the
+/// fastest.
+#[derive(Clone)]
+pub struct Nlms<T = Real> {
+    lms: Lms<T>,
+}
+
+impl<T: AdaptiveSample> Nlms<T> {
+    /// Added to the input energy to avoid dividing by zero when the input is
+    /// silent.
+    const REGULARIZATION: Real = 1e-6;
+
+    /// Construct a filter with all weights zero; see [`Lms::new`].
+    pub fn new(num_taps: usize, step_size: Real, leakage: Real) -> Self {
+        Self {
+            lms: Lms::new(num_taps, step_size, leakage),
+        }
+    }
+
+    pub fn set_step_size(&mut self, step_size: Real) {
+        self.lms.set_step_size(step_size);
+    }
+
+    pub fn set_leakage(&mut self, leakage: Real) {
+        self.lms.set_leakage(leakage);
+    }
+}
+
+impl<T: AdaptiveSample> AdaptiveFilter<T> for Nlms<T> {
+    fn estimate(&mut self, input: T) -> T {
+        self.lms.estimate(input)
+    }
+
+    fn adapt(&mut self, error: T) {
+        let inputs = self.lms.inputs.window();
+        let energy: Real = inputs.iter().map(Sample::magnitude_squared).sum();
+        let step = error.conj() * (self.lms.step_size / (energy + Self::REGULARIZATION));
+        update(&mut self.lms.weights, inputs, step, self.lms.leakage);
+    }
+
+    fn weights(&self) -> &[T] {
+        &self.lms.weights
+    }
+}
+
+/// Recursive least squares adaptive filter.
+///
+/// Minimizes the exponentially weighted sum of all past squared errors, with
+/// a weight of `forgetting_factor ^ age`. It converges much faster than
+/// [`Lms`] and [`Nlms`], independently of the input's spectrum, but costs
+/// O(`num_taps ^ 2`) per sample.
+#[derive(Clone)]
+pub struct Rls<T = Real> {
+    weights: Box<[T]>,
+    inputs: DelayLine<T>,
+    /// Inverse of the weighted input correlation matrix, row-major.
+    inverse_correlation: Box<[T]>,
+    /// `P u`, the gain vector before normalization.
+    gain: Box<[T]>,
+    forgetting_factor: Real,
+}
+
+impl<T: AdaptiveSample> Rls<T> {
+    /// Construct a filter with all weights zero.
+    ///
+    /// `forgetting_factor` is typically between 0.95 and 1; the filter's
+    /// memory is about `1 / (1 - forgetting_factor)` samples. The inverse
+    /// correlation matrix starts as the identity times `1 / regularization`,
+    /// where a small regularization (relative to the input power) gives
+    /// faster initial convergence.
+    pub fn new(num_taps: usize, forgetting_factor: Real, regularization: Real) -> Self {
+        assert!(num_taps > 0);
+        assert!(0.0 < forgetting_factor && forgetting_factor <= 1.0);
+        assert!(regularization > 0.0);
+        let mut inverse_correlation = vec![T::ZERO; num_taps * num_taps].into_boxed_slice();
+        for i in 0..num_taps {
+            inverse_correlation[i * num_taps + i] = T::from_re(1.0 / regularization);
+        }
+        Self {
+            weights: vec![T::ZERO; num_taps].into_boxed_slice(),
+            inputs: DelayLine::new(num_taps),
+            inverse_correlation,
+            gain: vec![T::ZERO; num_taps].into_boxed_slice(),
+            forgetting_factor,
+        }
+    }
+
+    pub fn set_forgetting_factor(&mut self, forgetting_factor: Real) {
+        self.forgetting_factor = forgetting_factor;
+    }
+}
+
+impl<T: AdaptiveSample> AdaptiveFilter<T> for Rls<T> {
+    fn estimate(&mut self, input: T) -> T {
+        self.inputs.push(input);
+        estimate(&self.weights, self.inputs.window())
+    }
+
+    fn adapt(&mut self, error: T) {
+        let n = self.weights.len();
+        let inputs = self.inputs.window();
+        let p = &mut self.inverse_correlation;
+
+        // gain = P u / (forgetting_factor + u^H P u)
+        for (i, gain) in self.gain.iter_mut().enumerate() {
+            *gain = estimate_row(&p[i * n..(i + 1) * n], inputs);
+        }
+        let denominator = self.forgetting_factor
+            + inputs
+                .iter()
+                .zip(&self.gain[..])
+                .map(|(&u, &pu)| (u.conj() * pu).re())
+                .sum::<Real>();
+
+        // P <- (P - gain (P u)^H) / forgetting_factor, using the fact that P
+        // is Hermitian so that u^H P = (P u)^H.
+        let scale = 1.0 / self.forgetting_factor;
+        for i in 0..n {
+            let gain_i = self.gain[i] / denominator;
+            for j in 0..n {
+                let pu_j = self.gain[j];
+                p[i * n + j] = (p[i * n + j] - gain_i * pu_j.conj()) * scale;
+            }
+        }
+
+        for (weight, &pu) in self.weights.iter_mut().zip(&self.gain[..]) {
+            *weight = *weight + pu / denominator * error.conj();
+        }
+    }
+
+    fn weights(&self) -> &[T] {
+        &self.weights
+    }
+}
+
+/// Row of a matrix times a vector, without conjugation.
+fn estimate_row<T: AdaptiveSample>(row: &[T], vector: &[T]) -> T {
+    row.iter().zip(vector).map(|(&a, &b)| a * b).sum()
+}
+
+/// Adaptive linear predictor, the common core of [`AutoNotch`] and
+/// [`LineEnhancer`].
+///
+/// The filter predicts each sample from the samples at least `delay` samples
+/// before it. Periodic components (tones, carriers) stay correlated over the
+/// delay and are predicted well, while broadband noise and fast-changing
+/// signals like speech are not.
+#[derive(Clone)]
+struct Predictor<T, F> {
+    filter: F,
+    inputs: DelayLine<T>,
+}
+
+impl<T: AdaptiveSample, F: AdaptiveFilter<T>> Predictor<T, F> {
+    fn new(filter: F, delay: usize) -> Self {
+        assert!(delay > 0);
+        Self {
+            filter,
+            inputs: DelayLine::new(delay + 1),
+        }
+    }
+
+    /// Returns the prediction and the prediction error.
+    fn process_sample(&mut self, sample: T) -> (T, T) {
+        self.inputs.push(sample);
+        let delayed = *self.inputs.window().last().unwrap();
+        self.filter.process_sample(delayed, sample)
+    }
+}
+
+/// Automatic notch filter, which finds and removes steady tones (carriers,
+/// heterodynes) while passing everything else.
+///
+/// The output is the error of an adaptive linear predictor, i.e. whatever
+/// can't be predicted from the signal's past.
+#[derive(Clone)]
+pub struct AutoNotch<T = Real, F = Nlms<T>> {
+    predictor: Predictor<T, F>,
+}
+
+impl<T: AdaptiveSample, F: AdaptiveFilter<T>> AutoNotch<T, F> {
+    /// Construct a notch using the given adaptive filter, predicting from the
+    /// inputs at least `delay` samples in the past. The delay should be
+    /// longer than the correlation time of the wanted signal.
+    pub fn new(filter: F, delay: usize) -> Self {
+        Self {
+            predictor: Predictor::new(filter, delay),
+        }
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.predictor.filter
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.predictor.process_sample(sample).1
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+impl<T: AdaptiveSample> AutoNotch<T, Nlms<T>> {
+    /// A notch with typical settings for audio at 8 kHz: a 32-tap NLMS filter,
+    /// a delay of 16 samples (2 ms), a slow step size and a small amount of
+    /// leakage.
+    pub fn nlms() -> Self {
+        Self::new(Nlms::new(32, 0.005, 1e-5), 16)
+    }
+}
+
+/// Adaptive line enhancer, which passes steady tones (e.g. a CW or PSK31
+/// signal) and suppresses broadband noise.
+///
+/// The output is the prediction of an adaptive linear predictor, which can
+/// only reproduce the periodic parts of the signal.
+#[derive(Clone)]
+pub struct LineEnhancer<T = Real, F = Nlms<T>> {
+    predictor: Predictor<T, F>,
+}
+
+impl<T: AdaptiveSample, F: AdaptiveFilter<T>> LineEnhancer<T, F> {
+    /// Construct a line enhancer using the given adaptive filter, predicting
+    /// from the inputs at least `delay` samples in the past.
+    pub fn new(filter: F, delay: usize) -> Self {
+        Self {
+            predictor: Predictor::new(filter, delay),
+        }
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.predictor.filter
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.predictor.process_sample(sample).0
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+impl<T: AdaptiveSample> LineEnhancer<T, Nlms<T>> {
+    /// A line enhancer with typical settings for audio at 8 kHz: a 64-tap NLMS
+    /// filter, a delay of 1 sample and a slow step size.
+    pub fn nlms() -> Self {
+        Self::new(Nlms::new(64, 0.002, 1e-6), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::TAU;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// The unknown system to identify.
+    const SYSTEM: [Real; 8] = [0.5, -0.3, 0.2, 0.9, -0.1, 0.05, -0.4, 0.15];
+
+    /// Feed `samples` of white noise through `SYSTEM` and adapt `filter` to
+    /// match it, then check that its weights are within `tolerance` of the
+    /// conjugated system.
+    fn identify<T, F>(
+        mut filter: F,
+        samples: usize,
+        tolerance: Real,
+        noise: impl Fn(&mut StdRng) -> T,
+    ) where
+        T: AdaptiveSample + std::fmt::Debug,
+        F: AdaptiveFilter<T>,
+    {
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut system = DelayLine::new(SYSTEM.len());
+        for _ in 0..samples {
+            let input = noise(&mut rng);
+            system.push(input);
+            let desired = T::weighted_sum(&SYSTEM, system.window());
+            filter.process_sample(input, desired);
+        }
+        for (k, (&weight, &expected)) in filter.weights().iter().zip(&SYSTEM).enumerate() {
+            let error = (weight - T::from_re(expected)).magnitude();
+            assert!(error < tolerance, "weight {k}: {weight:?} != {expected}");
+        }
+    }
+
+    fn real_noise(rng: &mut StdRng) -> Real {
+        rng.gen_range(-1.0..1.0)
+    }
+
+    fn complex_noise(rng: &mut StdRng) -> IQ {
+        IQ::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+    }
+
+    #[test]
+    fn lms_identifies_system() {
+        identify(Lms::new(8, 0.05, 0.0), 3000, 1e-3, real_noise);
+        identify(Lms::new(8, 0.05, 0.0), 3000, 1e-3, complex_noise);
+    }
+
+    #[test]
+    fn nlms_identifies_system() {
+        identify(Nlms::new(8, 0.5, 0.0), 500, 1e-3, real_noise);
+        identify(Nlms::new(8, 0.5, 0.0), 500, 1e-3, complex_noise);
+    }
+
+    #[test]
+    fn rls_identifies_system() {
+        identify(Rls::new(8, 0.999, 1e-3), 50, 1e-3, real_noise);
+        identify(Rls::new(8, 0.999, 1e-3), 50, 1e-3, complex_noise);
+    }
+
+    /// Power of the component of `signal` at `freq` cycles per sample, and
+    /// the power of the rest.
+    fn tone_and_residual(signal: &[Real], freq: Real) -> (Real, Real) {
+        let (i, q) = signal
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(i, q), (n, &x)| {
+                let angle = TAU * freq * n as Real;
+                (i + x * angle.cos(), q + x * angle.sin())
+            });
+        let len = signal.len() as Real;
+        let tone = 2.0 * (i * i + q * q) / (len * len);
+        let total = signal.iter().map(|x| x * x).sum::<Real>() / len;
+        (tone, total - tone)
+    }
+
+    #[test]
+    fn notch_removes_tone_and_enhancer_keeps_it() {
+        let mut rng = StdRng::seed_from_u64(16);
+        // An 800 Hz tone at 8 kHz, in white noise about 15 dB weaker.
+        let freq = 0.1;
+        let input: Vec<Real> = (0..40000)
+            .map(|n| (TAU * freq * n as Real).sin() + 0.2 * real_noise(&mut rng))
+            .collect();
+        // Measured once both filters have converged.
+        let settled = 30000;
+        let (tone, noise) = tone_and_residual(&input[settled..], freq);
+
+        let mut notched = input.clone();
+        AutoNotch::nlms().process_inplace(&mut notched);
+        let (notched_tone, notched_noise) = tone_and_residual(&notched[settled..], freq);
+        assert!(notched_tone < 1e-3 * tone, "tone {notched_tone}");
+        assert!(
+            (0.8..1.25).contains(&(notched_noise / noise)),
+            "noise {notched_noise} of {noise}"
+        );
+
+        let mut enhanced = input;
+        LineEnhancer::nlms().process_inplace(&mut enhanced);
+        let (enhanced_tone, enhanced_noise) = tone_and_residual(&enhanced[settled..], freq);
+        assert!(enhanced_tone > 0.9 * tone, "tone {enhanced_tone}");
+        assert!(
+            enhanced_noise < 0.1 * noise,
+            "noise {enhanced_noise} of {noise}"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Lightweight recursive filters that cost O(1) per sample.
+
+use crate::{
+    filter::Filter,
+    math::{Real, TAU},
+    sample::Sample,
+};
+
+/// Decay factor per sample for a time constant in seconds, matching the
+/// `exp(-t / time_constant)` decay of [`Window::Exponential`](super::Window).
+fn decay(time_constant: Real, sample_rate: Real) -> Real {
+    assert!(time_constant > 0.0 && sample_rate > 0.0);
+    (-1.0 / (time_constant * sample_rate)).exp()
+}
+
+/// Boxcar average of the last `len` samples.
+///
+/// A running sum is updated with each new and expired sample. To keep
+/// rounding error from accumulating, it is recomputed from scratch once every
+/// `len` samples, which is still O(1) per sample on average.
+#[derive(Clone)]
+pub struct MovingAverage<T = Real> {
+    buffer: Box<[T]>,
+    position: usize,
+    sum: T,
+}
+
+impl<T: Sample> MovingAverage<T> {
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0);
+        Self {
+            buffer: vec![T::ZERO; len].into_boxed_slice(),
+            position: 0,
+            sum: T::ZERO,
+        }
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        let expired = std::mem::replace(&mut self.buffer[self.position], sample);
+        self.position = (self.position + 1) % self.buffer.len();
+        self.sum = if self.position == 0 {
+            self.buffer.iter().copied().sum()
+        } else {
+            self.sum + sample - expired
+        };
+        self.sum / self.buffer.len() as Real
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+/// Removes the DC component of a signal.
+///
+/// `y[n] = x[n] - x[n-1] + r * y[n-1]`, a differentiator followed by a leaky
+/// integrator, with a zero at DC and a pole just inside it. The closer the
+/// pole is to DC, the narrower the notch.
+#[derive(Clone)]
+pub struct DcBlocker<T = Real> {
+    pole: Real,
+    last_input: T,
+    last_output: T,
+}
+
+impl<T: Sample> DcBlocker<T> {
+    /// Construct a DC blocker with its -3 dB point at roughly `cutoff` Hz.
+    pub fn new(cutoff: Real, sample_rate: Real) -> Self {
+        Self {
+            pole: decay(1.0 / (TAU * cutoff), sample_rate),
+            last_input: T::ZERO,
+            last_output: T::ZERO,
+        }
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.last_output = sample - self.last_input + self.last_output * self.pole;
+        self.last_input = sample;
+        self.last_output
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+/// Single-pole lowpass filter (exponential moving average) with unity gain
+/// at DC.
+///
+/// The impulse response decays as `exp(-t / time_constant)`, with
+/// `time_constant` in seconds, so a step input gets to within `1 / e` of its
+/// final value after one time constant.
+#[derive(Clone)]
+pub struct SinglePole<T = Real> {
+    decay: Real,
+    output: T,
+}
+
+impl<T: Sample> SinglePole<T> {
+    pub fn new(time_constant: Real, sample_rate: Real) -> Self {
+        Self {
+            decay: decay(time_constant, sample_rate),
+            output: T::ZERO,
+        }
+    }
+
+    /// The most recent output.
+    pub fn output(&self) -> T {
+        self.output
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.output = self.output * self.decay + sample * (1.0 - self.decay);
+        self.output
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+/// Running sum whose contents decay as `exp(-t / time_constant)`.
+///
+/// Unlike [`SinglePole`], the input isn't scaled, so a constant input `x`
+/// settles to about `x * time_constant * sample_rate`. Useful for
+/// accumulating energy or error terms over a sliding, exponentially
+/// weighted interval.
+#[derive(Clone)]
+pub struct LeakyIntegrator<T = Real> {
+    decay: Real,
+    output: T,
+}
+
+impl<T: Sample> LeakyIntegrator<T> {
+    pub fn new(time_constant: Real, sample_rate: Real) -> Self {
+        Self {
+            decay: decay(time_constant, sample_rate),
+            output: T::ZERO,
+        }
+    }
+
+    /// The most recent output.
+    pub fn output(&self) -> T {
+        self.output
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.output = self.output * self.decay + sample;
+        self.output
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+macro_rules! impl_filter {
+    ($($name:ident),*) => {
+        $(
+            impl<T: Sample> Filter<T> for $name<T> {
+                fn process_sample(&mut self, sample: T) -> T {
+                    $name::process_sample(self, sample)
+                }
+            }
+        )*
+    };
+}
+
+impl_filter!(MovingAverage, DcBlocker, SinglePole, LeakyIntegrator);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{iq::IQ, math::E};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn moving_average_matches_mean() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // A large offset makes any drift in the running sum show up.
+        let input: Vec<IQ> = (0..10000)
+            .map(|_| IQ::new(1000.0 + rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect();
+        for len in [1, 7, 64] {
+            let mut average = MovingAverage::new(len);
+            let mut output = input.clone();
+            average.process_inplace(&mut output);
+            for (n, &sample) in output.iter().enumerate() {
+                let window = &input[n.saturating_sub(len - 1)..=n];
+                let expected = window.iter().copied().sum::<IQ>() / len as Real;
+                assert!(
+                    (sample - expected).magnitude() < 1e-3,
+                    "{len}, {n}: {sample:?} != {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dc_blocker_removes_offset_and_passes_tone() {
+        let sample_rate = 48000.0;
+        let mut blocker = DcBlocker::new(10.0, sample_rate);
+        let tone = |n: usize| (TAU * (n % 48) as Real / 48.0).sin();
+        let mut output: Vec<Real> = (0..48000).map(|n| 0.5 + tone(n)).collect();
+        blocker.process_inplace(&mut output);
+
+        // Compare the last 10 ms, well past the 16 ms time constant.
+        let settled = &output[output.len() - 480..];
+        let mean = settled.iter().sum::<Real>() / settled.len() as Real;
+        assert!(mean.abs() < 1e-3, "{mean}");
+        for (n, &sample) in settled.iter().enumerate() {
+            let expected = tone(n);
+            assert!(
+                (sample - expected).abs() < 0.02,
+                "{n}: {sample} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn single_pole_step_response() {
+        let sample_rate = 8000.0;
+        let mut filter = SinglePole::new(0.01, sample_rate);
+        let mut output = vec![1.0; 800];
+        filter.process_inplace(&mut output);
+        // One time constant is 80 samples.
+        let expected = 1.0 - 1.0 / E;
+        assert!((output[79] - expected).abs() < 1e-3, "{}", output[79]);
+        assert!((filter.output() - 1.0).abs() < 1e-4);
+        assert!(output.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn leaky_integrator_settles() {
+        let sample_rate = 8000.0;
+        let time_constant = 0.01;
+        let mut integrator = LeakyIntegrator::new(time_constant, sample_rate);
+        let mut output = vec![0.25; 2000];
+        integrator.process_inplace(&mut output);
+        let exact = 0.25 / (1.0 - decay(time_constant, sample_rate));
+        assert!((integrator.output() - exact).abs() < 1e-3 * exact);
+        let approx = 0.25 * time_constant * sample_rate;
+        assert!((integrator.output() - approx).abs() < 0.01 * approx);
+
+        // With the input removed it decays by 1 / e per time constant.
+        let mut tail = vec![0.0; 80];
+        integrator.process_inplace(&mut tail);
+        assert!((tail[79] - exact / E).abs() < 1e-3 * exact);
+    }
+}
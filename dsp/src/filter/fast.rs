@@ -0,0 +1,224 @@
+//! Block FIR filtering using FFT-based fast convolution.
+
+use crate::{
+    fft::{Fft, FftSample},
+    filter::{kernel::FirSample, Fir},
+    iq::IQ,
+    math::Real,
+    sample::Sample,
+};
+
+/// FIR filter implemented with the overlap-save method.
+///
+/// Each block of input is prefixed with the last `num_taps - 1` input samples,
+/// transformed, multiplied by the spectrum of the taps and transformed back.
+/// The output is identical (within rounding error) to `Fir::process_inplace`
+/// with the same taps, and does not add any latency.
+#[derive(Clone)]
+pub struct OverlapSave<T = Real> {
+    fft: Fft,
+    taps: Box<[Real]>,
+    spectrum: Box<[IQ]>,
+    history: Box<[T]>,
+    work: Box<[IQ]>,
+    block_size: usize,
+}
+
+impl<T: FftSample> OverlapSave<T> {
+    /// Construct a filter that processes up to `block_size` samples per FFT.
+    ///
+    /// Buffers of any length can be processed; longer buffers are split into
+    /// blocks of `block_size`.
+    pub fn new(taps: impl Into<Box<[Real]>>, block_size: usize) -> Self {
+        let taps = taps.into();
+        assert!(!taps.is_empty());
+        assert!(block_size > 0);
+
+        let fft_size = Self::fft_size(taps.len(), block_size);
+        let fft = Fft::new(fft_size);
+
+        let mut spectrum = vec![IQ::ZERO; fft_size].into_boxed_slice();
+        for (slot, &tap) in spectrum.iter_mut().zip(&taps[..]) {
+            *slot = IQ::new(tap, 0.0);
+        }
+        fft.forward(&mut spectrum);
+
+        Self {
+            fft,
+            history: vec![T::ZERO; taps.len() - 1].into_boxed_slice(),
+            work: vec![IQ::ZERO; fft_size].into_boxed_slice(),
+            taps,
+            spectrum,
+            block_size,
+        }
+    }
+
+    fn fft_size(num_taps: usize, block_size: usize) -> usize {
+        (num_taps - 1 + block_size).next_power_of_two()
+    }
+
+    pub fn taps(&self) -> &[Real] {
+        &self.taps
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for block in buffer.chunks_mut(self.block_size) {
+            self.process_block(block);
+        }
+    }
+
+    fn process_block(&mut self, block: &mut [T]) {
+        let overlap = self.history.len();
+
+        for (slot, &sample) in self.work.iter_mut().zip(self.history.iter().chain(&*block)) {
+            *slot = sample.to_iq();
+        }
+        self.work[overlap + block.len()..].fill(IQ::ZERO);
+
+        // Save the end of the input for the next block before overwriting it.
+        if block.len() >= overlap {
+            self.history
+                .copy_from_slice(&block[block.len() - overlap..]);
+        } else {
+            self.history.copy_within(block.len().., 0);
+            self.history[overlap - block.len()..].copy_from_slice(block);
+        }
+
+        self.fft.forward(&mut self.work);
+        for (slot, &h) in self.work.iter_mut().zip(&self.spectrum[..]) {
+            *slot *= h;
+        }
+        self.fft.inverse(&mut self.work);
+
+        // The first `overlap` outputs are corrupted by circular wraparound;
+        // the rest are valid linear convolution outputs.
+        for (slot, &value) in block.iter_mut().zip(&self.work[overlap..]) {
+            *slot = T::from_iq(value);
+        }
+    }
+}
+
+/// Block FIR filter that automatically chooses between direct convolution and
+/// overlap-save, whichever is estimated to be cheaper for the given number of
+/// taps and block size.
+#[derive(Clone)]
+pub enum FastFir<T = Real> {
+    Direct(Fir<T>),
+    OverlapSave(OverlapSave<T>),
+}
+
+impl<T: FftSample + FirSample> FastFir<T> {
+    pub fn new(taps: impl Into<Box<[Real]>>, block_size: usize) -> Self {
+        let taps = taps.into();
+
+        // Rough operation counts per block: `num_taps` multiply-adds per
+        // output for direct convolution, versus a forward and inverse
+        // radix-2 FFT (about 2.5 N log2(N) real multiply-adds each) plus the
+        // spectrum multiplication for overlap-save.
+        let fft_size = OverlapSave::<T>::fft_size(taps.len(), block_size);
+        let direct_cost = taps.len() * block_size;
+        let fft_cost = 5 * fft_size * fft_size.trailing_zeros() as usize + 4 * fft_size;
+
+        if fft_cost < direct_cost {
+            Self::OverlapSave(OverlapSave::new(taps, block_size))
+        } else {
+            Self::Direct(Fir::new(taps))
+        }
+    }
+
+    pub fn taps(&self) -> &[Real] {
+        match self {
+            Self::Direct(fir) => fir.taps(),
+            Self::OverlapSave(overlap_save) => overlap_save.taps(),
+        }
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        match self {
+            Self::Direct(fir) => fir.process_inplace(buffer),
+            Self::OverlapSave(overlap_save) => overlap_save.process_inplace(buffer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_taps(rng: &mut StdRng, len: usize) -> Vec<Real> {
+        (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect()
+    }
+
+    fn random_signal(rng: &mut StdRng, len: usize) -> Vec<IQ> {
+        (0..len)
+            .map(|_| IQ::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect()
+    }
+
+    /// Filter `input` with `Fir`, and with `process` fed the chunks given by
+    /// `splits`, and compare the results.
+    fn check<T: FftSample + FirSample + std::fmt::Debug>(
+        taps: &[Real],
+        input: &[T],
+        splits: &[usize],
+        mut process: impl FnMut(&mut [T]),
+    ) {
+        let mut expected = input.to_vec();
+        Fir::new(taps.to_vec()).process_inplace(&mut expected);
+
+        let mut output = input.to_vec();
+        let mut rest = &mut output[..];
+        for &split in splits {
+            let (chunk, tail) = rest.split_at_mut(split);
+            process(chunk);
+            rest = tail;
+        }
+        process(rest);
+
+        // Overlap-save rounding grows with the size of the sums.
+        let tolerance = 64.0 * Real::EPSILON * taps.len() as Real;
+        for (n, (&a, &e)) in output.iter().zip(&expected).enumerate() {
+            assert!(
+                (a - e).magnitude() < tolerance,
+                "sample {n}: {a:?} != {e:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn overlap_save_matches_fir() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for (num_taps, block_size) in [(1, 16), (7, 1), (31, 64), (64, 100), (257, 256)] {
+            let taps = random_taps(&mut rng, num_taps);
+            let input = random_signal(&mut rng, 1000);
+            // Whole blocks, partial blocks, and chunks shorter than the taps.
+            for splits in [&[][..], &[256, 512], &[3, 5, 100, 333]] {
+                let mut filter = OverlapSave::new(taps.clone(), block_size);
+                check(&taps, &input, splits, |chunk| filter.process_inplace(chunk));
+
+                let real: Vec<Real> = input.iter().map(|x| x.i).collect();
+                let mut filter = OverlapSave::new(taps.clone(), block_size);
+                check(&taps, &real, splits, |chunk| filter.process_inplace(chunk));
+            }
+        }
+    }
+
+    #[test]
+    fn fast_fir_matches_fir() {
+        let mut rng = StdRng::seed_from_u64(6);
+        for (num_taps, block_size, direct) in [(8, 64, true), (512, 1024, false)] {
+            let taps = random_taps(&mut rng, num_taps);
+            let input = random_signal(&mut rng, 3000);
+            let mut filter = FastFir::new(taps.clone(), block_size);
+            assert_eq!(matches!(filter, FastFir::Direct(_)), direct);
+            check(&taps, &input, &[1000, 10], |chunk| {
+                filter.process_inplace(chunk)
+            });
+        }
+    }
+}
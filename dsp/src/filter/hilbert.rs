@@ -0,0 +1,144 @@
+//! Hilbert transformer design and real-to-analytic signal conversion.
+
+use crate::{
+    filter::{kernel::FirSample, Fir, Window},
+    iq::IQ,
+    math::{Real, PI},
+};
+
+/// Windowed FIR approximation of the Hilbert transform.
+///
+/// The ideal transformer shifts every positive frequency by -90 degrees (so
+/// `cos` becomes `sin`), with an impulse response of `2 / (PI * n)` for odd
+/// `n` and zero for even `n`. It is truncated to `num_taps` taps (rounded up to
+/// be odd) and tapered by `window`, which trades off ripple against how close
+/// to zero and Nyquist the response stays flat.
+#[derive(Debug, Clone, Copy)]
+pub struct Hilbert {
+    pub num_taps: usize,
+    pub window: Window,
+}
+
+impl Hilbert {
+    pub fn num_taps(&self) -> usize {
+        // Ensure num_taps is odd
+        self.num_taps | 1
+    }
+
+    /// Delay of the transformer in samples, i.e. the index of the center tap.
+    pub fn delay(&self) -> usize {
+        self.num_taps() / 2
+    }
+
+    pub fn taps(&self) -> Box<[Real]> {
+        let num_taps = self.num_taps();
+        let center = self.delay() as isize;
+        let span = (num_taps - 1).max(1) as Real;
+
+        (0..num_taps)
+            .map(|x| {
+                let n = x as isize - center;
+                if n % 2 == 0 {
+                    0.0
+                } else {
+                    2.0 / (PI * n as Real) * self.window.sample(x as Real, span)
+                }
+            })
+            .collect()
+    }
+
+    pub fn build<T: FirSample>(&self) -> Fir<T> {
+        Fir::new(self.taps())
+    }
+}
+
+/// Converts a stream of real samples into the corresponding analytic signal.
+///
+/// The output's in-phase component is the input, and its quadrature
+/// component is the Hilbert transform of the input, so negative frequencies
+/// are suppressed. The in-phase path is delayed to line up with the Hilbert
+/// transformer, so the whole block has a delay of `delay()` samples.
+pub struct AnalyticSignal {
+    hilbert: Fir<Real>,
+    delay_line: Box<[Real]>,
+    position: usize,
+}
+
+impl AnalyticSignal {
+    pub fn new(design: Hilbert) -> Self {
+        Self {
+            hilbert: design.build(),
+            delay_line: vec![0.0; design.delay() + 1].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    /// Delay from input to output, in samples.
+    pub fn delay(&self) -> usize {
+        self.delay_line.len() - 1
+    }
+
+    pub fn process_sample(&mut self, sample: Real) -> IQ {
+        self.delay_line[self.position] = sample;
+        self.position = (self.position + 1) % self.delay_line.len();
+        let delayed = self.delay_line[self.position];
+        IQ::new(delayed, self.hilbert.process_sample(sample))
+    }
+
+    pub fn process(&mut self, input: &[Real], output: &mut [IQ]) {
+        assert_eq!(input.len(), output.len());
+        for (inp, out) in input.iter().zip(output) {
+            *out = self.process_sample(*inp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::{FRAC_PI_2, TAU},
+        sample::Sample,
+    };
+
+    #[test]
+    fn analytic_signal_of_sinusoid() {
+        let design = Hilbert {
+            num_taps: 63,
+            window: Window::BLACKMAN,
+        };
+        for cycles_per_sample in [0.05, 0.125, 0.25, 0.4] {
+            let mut analytic = AnalyticSignal::new(design);
+            assert_eq!(analytic.delay(), 31);
+            let input: Vec<Real> = (0..2000)
+                .map(|n| (TAU * cycles_per_sample * n as Real).cos())
+                .collect();
+            let mut output = vec![IQ::ZERO; input.len()];
+            analytic.process(&input, &mut output);
+
+            // Skip the group delay plus a full filter length of warm-up.
+            let start = analytic.delay() + design.num_taps();
+            let (mut positive, mut negative) = (IQ::ZERO, IQ::ZERO);
+            let (mut in_phase, mut quadrature) = (IQ::ZERO, IQ::ZERO);
+            let mut max_error: Real = 0.0;
+            for (n, &sample) in output.iter().enumerate().skip(start) {
+                let phase = TAU * cycles_per_sample * (n - analytic.delay()) as Real;
+                let phasor = IQ::new_polar(phase, 1.0);
+                positive = positive + sample * phasor.conj();
+                negative = negative + sample * phasor;
+                in_phase = in_phase + phasor.conj() * sample.i;
+                quadrature = quadrature + phasor.conj() * sample.q;
+                max_error = max_error.max((sample - phasor).magnitude());
+            }
+
+            let image = negative.magnitude() / positive.magnitude();
+            assert!(image < 0.003, "{cycles_per_sample}: image {image}");
+            let lag = (in_phase * quadrature.conj()).phase();
+            assert!(
+                (lag - FRAC_PI_2).abs() < 0.01,
+                "{cycles_per_sample}: I/Q phase {lag}"
+            );
+            assert!(max_error < 1e-3, "{cycles_per_sample}: error {max_error}");
+        }
+    }
+}
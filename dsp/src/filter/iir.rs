@@ -0,0 +1,762 @@
+//! IIR filters built from cascaded second-order sections, and designers for
+//! the classic analog prototypes.
+
+use num_complex::Complex;
+
+use crate::{
+    filter::{Filter, Passband},
+    math::Real,
+    sample::Sample,
+};
+
+type C64 = Complex<f64>;
+
+/// Coefficients of a single second-order section, normalized so that `a0 = 1`:
+///
+/// ```text
+///        b[0] + b[1] z^-1 + b[2] z^-2
+/// H(z) = ----------------------------
+///          1  + a[0] z^-1 + a[1] z^-2
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b: [Real; 3],
+    pub a: [Real; 2],
+}
+
+/// A cascade of second-order sections, each in transposed direct form II.
+#[derive(Clone)]
+pub struct Iir<T = Real> {
+    sections: Box<[Biquad]>,
+    state: Box<[[T; 2]]>,
+}
+
+impl<T: Sample> Iir<T> {
+    pub fn new(sections: impl Into<Box<[Biquad]>>) -> Self {
+        let sections = sections.into();
+        let state = vec![[T::ZERO; 2]; sections.len()].into_boxed_slice();
+        Self { sections, state }
+    }
+
+    pub fn sections(&self) -> &[Biquad] {
+        &self.sections
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        let mut x = sample;
+        for (section, state) in self.sections.iter().zip(&mut self.state[..]) {
+            let y = x * section.b[0] + state[0];
+            state[0] = x * section.b[1] - y * section.a[0] + state[1];
+            state[1] = x * section.b[2] - y * section.a[1];
+            x = y;
+        }
+        x
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+
+    pub fn decimate(&mut self, buffer: &[T]) -> T {
+        buffer
+            .iter()
+            .map(|&sample| self.process_sample(sample))
+            .last()
+            .unwrap()
+    }
+}
+
+impl<T: Sample> Filter<T> for Iir<T> {
+    fn process_sample(&mut self, sample: T) -> T {
+        Iir::process_sample(self, sample)
+    }
+}
+
+/// Analog lowpass prototype used by `IirDesign`.
+#[derive(Debug, Clone, Copy)]
+pub enum Prototype {
+    /// Maximally flat passband; cutoff frequencies are the -3 dB points.
+    Butterworth,
+    /// Equiripple passband with the given peak-to-peak ripple in dB; cutoff
+    /// frequencies are the edges of the ripple band.
+    ChebyshevI { ripple: Real },
+    /// Equiripple stopband with the given minimum attenuation in dB; cutoff
+    /// frequencies are the edges of the stopband.
+    ChebyshevII { attenuation: Real },
+    /// Maximally flat group delay; cutoff frequencies are the -3 dB points.
+    Bessel,
+    /// Equiripple passband and stopband; cutoff frequencies are the edges of
+    /// the ripple band. The transition width follows from the order and the
+    /// two ripple specs.
+    Elliptic { ripple: Real, attenuation: Real },
+}
+
+/// IIR filter design via an analog prototype and the bilinear transform.
+///
+/// `order` is the order of the lowpass prototype. Band-pass and band-reject
+/// designs end up with twice that order.
+#[derive(Debug, Clone, Copy)]
+pub struct IirDesign {
+    pub gain: Real,
+    pub sample_rate: Real,
+    pub passband: Passband,
+    pub order: usize,
+    pub prototype: Prototype,
+}
+
+impl IirDesign {
+    pub fn sections(&self) -> Box<[Biquad]> {
+        assert!(self.order > 0);
+
+        let zpk = self.prototype.zpk(self.order);
+
+        // Pre-warp the cutoff frequencies to compensate for the bilinear
+        // transform.
+        let fs = self.sample_rate as f64;
+        let warp = |freq: Real| {
+            let freq = freq as f64;
+            assert!(
+                0.0 < freq && freq < 0.5 * fs,
+                "cutoff must be within 0..sample_rate/2"
+            );
+            2.0 * fs * (std::f64::consts::PI * freq / fs).tan()
+        };
+
+        let zpk = match self.passband {
+            Passband::LowPass { cutoff } => zpk.lowpass(warp(cutoff)),
+            Passband::HighPass { cutoff } => zpk.highpass(warp(cutoff)),
+            Passband::BandPass {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let (low, high) = (warp(low_cutoff), warp(high_cutoff));
+                zpk.bandpass((low * high).sqrt(), high - low)
+            }
+            Passband::BandReject {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let (low, high) = (warp(low_cutoff), warp(high_cutoff));
+                zpk.bandstop((low * high).sqrt(), high - low)
+            }
+        };
+
+        zpk.bilinear(fs).into_sections(self.gain as f64)
+    }
+
+    pub fn build<T: Sample>(&self) -> Iir<T> {
+        Iir::new(self.sections())
+    }
+}
+
+impl Prototype {
+    /// Poles, zeros and gain of the prototype, normalized to a cutoff of
+    /// 1 rad/s.
+    fn zpk(&self, order: usize) -> Zpk {
+        let n = order as f64;
+        match *self {
+            Self::Butterworth => {
+                let poles: Vec<C64> = (0..order)
+                    .map(|k| {
+                        C64::from_polar(
+                            1.0,
+                            std::f64::consts::PI * (2 * k + order + 1) as f64 / (2.0 * n),
+                        )
+                    })
+                    .collect();
+                Zpk::new(Vec::new(), poles, 1.0)
+            }
+            Self::ChebyshevI { ripple } => {
+                let epsilon = (10f64.powf(0.1 * ripple as f64) - 1.0).sqrt();
+                let mu = (1.0 / epsilon).asinh() / n;
+                let poles: Vec<C64> = (0..order)
+                    .map(|k| {
+                        let theta = std::f64::consts::PI * (2 * k + 1) as f64 / (2.0 * n);
+                        C64::new(-mu.sinh() * theta.sin(), mu.cosh() * theta.cos())
+                    })
+                    .collect();
+                let mut k = poles.iter().map(|p| -p).product::<C64>().re;
+                if order.is_multiple_of(2) {
+                    k /= (1.0 + epsilon * epsilon).sqrt();
+                }
+                Zpk {
+                    zeros: Vec::new(),
+                    poles,
+                    gain: k,
+                }
+            }
+            Self::ChebyshevII { attenuation } => {
+                let epsilon = 1.0 / (10f64.powf(0.1 * attenuation as f64) - 1.0).sqrt();
+                let mu = (1.0 / epsilon).asinh() / n;
+                let mut zeros = Vec::new();
+                let mut poles = Vec::new();
+                for k in 0..order {
+                    let theta = std::f64::consts::PI * (2 * k + 1) as f64 / (2.0 * n);
+                    // The middle zero of an odd-order filter is at infinity.
+                    if 2 * k + 1 != order {
+                        zeros.push(C64::new(0.0, 1.0 / theta.cos()));
+                    }
+                    poles.push(C64::new(-mu.sinh() * theta.sin(), mu.cosh() * theta.cos()).inv());
+                }
+                Zpk::new(zeros, poles, 1.0)
+            }
+            Self::Bessel => Zpk::new(Vec::new(), bessel_poles(order), 1.0),
+            Self::Elliptic {
+                ripple,
+                attenuation,
+            } => elliptic_zpk(order, ripple as f64, attenuation as f64),
+        }
+    }
+}
+
+/// Analog or digital filter in zero-pole-gain form.
+#[derive(Debug, Clone)]
+struct Zpk {
+    zeros: Vec<C64>,
+    poles: Vec<C64>,
+    gain: f64,
+}
+
+impl Zpk {
+    /// Construct an analog filter with the given DC gain.
+    fn new(zeros: Vec<C64>, poles: Vec<C64>, dc_gain: f64) -> Self {
+        let gain = dc_gain
+            * (poles.iter().map(|p| -p).product::<C64>()
+                / zeros.iter().map(|z| -z).product::<C64>())
+            .re;
+        Self { zeros, poles, gain }
+    }
+
+    fn degree(&self) -> usize {
+        self.poles.len() - self.zeros.len()
+    }
+
+    fn lowpass(self, cutoff: f64) -> Self {
+        let degree = self.degree() as i32;
+        Self {
+            zeros: self.zeros.iter().map(|z| z * cutoff).collect(),
+            poles: self.poles.iter().map(|p| p * cutoff).collect(),
+            gain: self.gain * cutoff.powi(degree),
+        }
+    }
+
+    fn highpass(self, cutoff: f64) -> Self {
+        let degree = self.degree();
+        let gain = self.gain
+            * (self.zeros.iter().map(|z| -z).product::<C64>()
+                / self.poles.iter().map(|p| -p).product::<C64>())
+            .re;
+        let mut zeros: Vec<C64> = self.zeros.iter().map(|z| cutoff / z).collect();
+        zeros.extend(std::iter::repeat_n(C64::new(0.0, 0.0), degree));
+        Self {
+            zeros,
+            poles: self.poles.iter().map(|p| cutoff / p).collect(),
+            gain,
+        }
+    }
+
+    fn bandpass(self, center: f64, bandwidth: f64) -> Self {
+        let degree = self.degree();
+        let transform = |roots: &[C64]| -> Vec<C64> {
+            roots
+                .iter()
+                .flat_map(|r| {
+                    let r = r * (0.5 * bandwidth);
+                    let offset = (r * r - center * center).sqrt();
+                    [r + offset, r - offset]
+                })
+                .collect()
+        };
+        let mut zeros = transform(&self.zeros);
+        zeros.extend(std::iter::repeat_n(C64::new(0.0, 0.0), degree));
+        Self {
+            zeros,
+            poles: transform(&self.poles),
+            gain: self.gain * bandwidth.powi(degree as i32),
+        }
+    }
+
+    fn bandstop(self, center: f64, bandwidth: f64) -> Self {
+        let degree = self.degree();
+        let gain = self.gain
+            * (self.zeros.iter().map(|z| -z).product::<C64>()
+                / self.poles.iter().map(|p| -p).product::<C64>())
+            .re;
+        let transform = |roots: &[C64]| -> Vec<C64> {
+            roots
+                .iter()
+                .flat_map(|r| {
+                    let r = (0.5 * bandwidth) / r;
+                    let offset = (r * r - center * center).sqrt();
+                    [r + offset, r - offset]
+                })
+                .collect()
+        };
+        let mut zeros = transform(&self.zeros);
+        for _ in 0..degree {
+            zeros.push(C64::new(0.0, center));
+            zeros.push(C64::new(0.0, -center));
+        }
+        Self {
+            zeros,
+            poles: transform(&self.poles),
+            gain,
+        }
+    }
+
+    /// Map an analog filter to the z-plane using the bilinear transform.
+    fn bilinear(self, sample_rate: f64) -> Self {
+        let fs2 = 2.0 * sample_rate;
+        let degree = self.degree();
+        let gain = self.gain
+            * (self.zeros.iter().map(|z| fs2 - z).product::<C64>()
+                / self.poles.iter().map(|p| fs2 - p).product::<C64>())
+            .re;
+        let mut zeros: Vec<C64> = self.zeros.iter().map(|z| (fs2 + z) / (fs2 - z)).collect();
+        zeros.extend(std::iter::repeat_n(C64::new(-1.0, 0.0), degree));
+        Self {
+            zeros,
+            poles: self.poles.iter().map(|p| (fs2 + p) / (fs2 - p)).collect(),
+            gain,
+        }
+    }
+
+    /// Group the digital poles and zeros into second-order sections.
+    ///
+    /// Poles closest to the unit circle are paired with the zeros closest to
+    /// them, which keeps the gain of each section well-behaved.
+    fn into_sections(self, extra_gain: f64) -> Box<[Biquad]> {
+        let mut pole_groups = conjugate_groups(&self.poles);
+        let mut zero_groups = conjugate_groups(&self.zeros);
+        pole_groups.sort_by(|a, b| a[0].norm().total_cmp(&b[0].norm()).reverse());
+
+        let mut sections: Vec<Biquad> = Vec::with_capacity(pole_groups.len());
+        for poles in &pole_groups {
+            let nearest = (0..zero_groups.len()).min_by(|&i, &j| {
+                let distance = |group: &Vec<C64>| (group[0] - poles[0]).norm();
+                distance(&zero_groups[i]).total_cmp(&distance(&zero_groups[j]))
+            });
+            let zeros = match nearest {
+                Some(i) => zero_groups.swap_remove(i),
+                None => Vec::new(),
+            };
+            sections.push(Biquad {
+                b: polynomial(&zeros),
+                a: {
+                    let [_, a1, a2] = polynomial(poles);
+                    [a1, a2]
+                },
+            });
+        }
+
+        // Apply the overall gain to the first section.
+        let gain = (self.gain * extra_gain) as Real;
+        if let Some(first) = sections.first_mut() {
+            for b in &mut first.b {
+                *b *= gain;
+            }
+        }
+        sections.into_boxed_slice()
+    }
+}
+
+/// Split roots into groups of at most two: complex conjugate pairs, and pairs
+/// of real roots. Roots with a negative imaginary part are assumed to be the
+/// conjugates of roots with a positive one.
+fn conjugate_groups(roots: &[C64]) -> Vec<Vec<C64>> {
+    const TOLERANCE: f64 = 1e-9;
+
+    let mut groups: Vec<Vec<C64>> = roots
+        .iter()
+        .filter(|r| r.im > TOLERANCE)
+        .map(|&r| vec![r, r.conj()])
+        .collect();
+
+    let mut reals: Vec<C64> = roots
+        .iter()
+        .filter(|r| r.im.abs() <= TOLERANCE)
+        .map(|r| C64::new(r.re, 0.0))
+        .collect();
+    reals.sort_by(|a, b| a.re.total_cmp(&b.re));
+    groups.extend(reals.chunks(2).map(|chunk| chunk.to_vec()));
+    groups
+}
+
+/// Coefficients of the monic polynomial with the given (at most two) roots.
+fn polynomial(roots: &[C64]) -> [Real; 3] {
+    match *roots {
+        [] => [1.0, 0.0, 0.0],
+        [r] => [1.0, -r.re as Real, 0.0],
+        [r1, r2] => [1.0, -(r1 + r2).re as Real, (r1 * r2).re as Real],
+        _ => unreachable!(),
+    }
+}
+
+/// Poles of the Bessel filter, normalized for -3 dB at 1 rad/s.
+fn bessel_poles(order: usize) -> Vec<C64> {
+    let coeffs = bessel_coefficients(order);
+    let mut poles = polynomial_roots(&coeffs);
+
+    // Find the -3 dB frequency by bisection and scale it to 1 rad/s.
+    let magnitude_squared = |w: f64| {
+        let value: C64 = coeffs
+            .iter()
+            .rev()
+            .fold(C64::new(0.0, 0.0), |acc, &c| acc * C64::new(0.0, w) + c);
+        (coeffs[0] * coeffs[0]) / value.norm_sqr()
+    };
+    let (mut low, mut high) = (0.0, 1.0);
+    while magnitude_squared(high) > 0.5 {
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        if magnitude_squared(mid) > 0.5 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let cutoff = 0.5 * (low + high);
+    for pole in &mut poles {
+        *pole /= cutoff;
+    }
+    poles
+}
+
+/// Coefficients of the reverse Bessel polynomial of the given order, lowest
+/// power first: `a[k] = (2n - k)! / (2^(n - k) k! (n - k)!)`.
+fn bessel_coefficients(order: usize) -> Vec<f64> {
+    let mut coeffs = vec![0.0; order + 1];
+    coeffs[order] = 1.0;
+    for k in (0..order).rev() {
+        let (n, k) = (order as f64, k as f64);
+        coeffs[k as usize] = coeffs[k as usize + 1] * (2.0 * n - k) * (k + 1.0) / (2.0 * (n - k));
+    }
+    coeffs
+}
+
+/// Roots of a real polynomial (coefficients lowest power first) using the
+/// Durand-Kerner iteration.
+fn polynomial_roots(coeffs: &[f64]) -> Vec<C64> {
+    let degree = coeffs.len() - 1;
+    let leading = coeffs[degree];
+    let evaluate = |x: C64| {
+        coeffs
+            .iter()
+            .rev()
+            .fold(C64::new(0.0, 0.0), |acc, &c| acc * x + c / leading)
+    };
+
+    let radius = (coeffs[0] / leading)
+        .abs()
+        .powf(1.0 / degree as f64)
+        .max(1.0);
+    let seed = C64::new(0.4, 0.9);
+    let mut roots: Vec<C64> = (0..degree).map(|k| seed.powi(k as i32) * radius).collect();
+    for _ in 0..1000 {
+        let mut change = 0.0f64;
+        for i in 0..degree {
+            let denominator: C64 = (0..degree)
+                .filter(|&j| j != i)
+                .map(|j| roots[i] - roots[j])
+                .product();
+            let delta = evaluate(roots[i]) / denominator;
+            roots[i] -= delta;
+            change = change.max(delta.norm() / roots[i].norm());
+        }
+        if change < 1e-14 {
+            break;
+        }
+    }
+    roots
+}
+
+/// Elliptic (Cauer) prototype, following Orfanidis, "Lecture Notes on
+/// Elliptic Filter Design".
+fn elliptic_zpk(order: usize, ripple: f64, attenuation: f64) -> Zpk {
+    let n = order as f64;
+    let ep = (10f64.powf(0.1 * ripple) - 1.0).sqrt();
+    let es = (10f64.powf(0.1 * attenuation) - 1.0).sqrt();
+    let k1 = ep / es;
+    let k = elliptic_degree(order, k1);
+
+    let j = C64::new(0.0, 1.0);
+    let v0 = -j * asne(j / ep, k1) / n;
+
+    let mut zeros = Vec::new();
+    let mut poles = Vec::new();
+    for i in 1..=order / 2 {
+        let u = (2 * i - 1) as f64 / n;
+        let zeta = cde(C64::new(u, 0.0), k);
+        let zero = j / (zeta * k);
+        let pole = j * cde(u - j * v0, k);
+        zeros.extend([zero, zero.conj()]);
+        poles.extend([pole, pole.conj()]);
+    }
+    if order % 2 == 1 {
+        poles.push(j * sne(j * v0, k));
+    }
+
+    // Odd orders have unity gain at DC; even orders start at the bottom of
+    // the passband ripple.
+    let dc_gain = if order % 2 == 1 {
+        1.0
+    } else {
+        1.0 / (1.0 + ep * ep).sqrt()
+    };
+    Zpk::new(zeros, poles, dc_gain)
+}
+
+/// Descending Landen sequence of elliptic moduli.
+fn landen(k: f64) -> Vec<f64> {
+    let mut k = k;
+    let mut sequence = Vec::new();
+    while k > 1e-15 && sequence.len() < 10 {
+        k = (k / (1.0 + (1.0 - k * k).sqrt())).powi(2);
+        sequence.push(k);
+    }
+    sequence
+}
+
+/// Complete elliptic integral of the first kind, `K(k)`.
+fn ellipk(k: f64) -> f64 {
+    let kmin: f64 = 1e-6;
+    let kmax = (1.0 - kmin * kmin).sqrt();
+    if k > kmax {
+        let kp = (1.0 - k * k).sqrt();
+        let l = -(kp / 4.0).ln();
+        l + (l - 1.0) * kp * kp / 4.0
+    } else {
+        landen(k).iter().map(|v| 1.0 + v).product::<f64>() * std::f64::consts::FRAC_PI_2
+    }
+}
+
+/// Jacobi elliptic `cd(u K, k)` for complex `u`, via Landen's transformation.
+fn cde(u: C64, k: f64) -> C64 {
+    let mut w = (u * std::f64::consts::FRAC_PI_2).cos();
+    for &v in landen(k).iter().rev() {
+        w = (1.0 + v) * w / (1.0 + v * w * w);
+    }
+    w
+}
+
+/// Jacobi elliptic `sn(u K, k)` for complex `u`, via Landen's transformation.
+fn sne(u: C64, k: f64) -> C64 {
+    let mut w = (u * std::f64::consts::FRAC_PI_2).sin();
+    for &v in landen(k).iter().rev() {
+        w = (1.0 + v) * w / (1.0 + v * w * w);
+    }
+    w
+}
+
+/// Inverse of `cde`, normalized to the fundamental period rectangle.
+fn acde(w: C64, k: f64) -> C64 {
+    let sequence = landen(k);
+    let mut w = w;
+    let mut previous = k;
+    for &v in &sequence {
+        w = w / (1.0 + (1.0 - w * w * previous * previous).sqrt()) * 2.0 / (1.0 + v);
+        previous = v;
+    }
+    let u = w.acos() * std::f64::consts::FRAC_2_PI;
+
+    let ratio = ellipk((1.0 - k * k).sqrt()) / ellipk(k);
+    let symmetric_remainder = |x: f64, y: f64| x - y * (x / y).round();
+    C64::new(
+        symmetric_remainder(u.re, 4.0),
+        symmetric_remainder(u.im, 2.0 * ratio),
+    )
+}
+
+/// Inverse of `sne`.
+fn asne(w: C64, k: f64) -> C64 {
+    1.0 - acde(w, k)
+}
+
+/// Solve the degree equation for the selectivity `k` of an elliptic filter of
+/// the given order and discrimination `k1`.
+fn elliptic_degree(order: usize, k1: f64) -> f64 {
+    let n = order as f64;
+    let k1p = (1.0 - k1 * k1).sqrt();
+    let product: f64 = (1..=order / 2)
+        .map(|i| sne(C64::new((2 * i - 1) as f64 / n, 0.0), k1p).re)
+        .product();
+    let kp = k1p.powi(order as i32) * product.powi(4);
+    (1.0 - kp * kp).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: Real = 8000.0;
+
+    fn lowpass(order: usize, prototype: Prototype) -> Box<[Biquad]> {
+        design(Passband::LowPass { cutoff: 1000.0 }, order, prototype)
+    }
+
+    fn design(passband: Passband, order: usize, prototype: Prototype) -> Box<[Biquad]> {
+        IirDesign {
+            gain: 1.0,
+            sample_rate: SAMPLE_RATE,
+            passband,
+            order,
+            prototype,
+        }
+        .sections()
+    }
+
+    fn response(sections: &[Biquad], freq: f64) -> C64 {
+        let z1 = C64::from_polar(1.0, -std::f64::consts::TAU * freq / SAMPLE_RATE as f64);
+        sections
+            .iter()
+            .map(|s| {
+                let [b0, b1, b2] = s.b.map(|b| b as f64);
+                let [a1, a2] = s.a.map(|a| a as f64);
+                (b0 + z1 * (b1 + z1 * b2)) / (1.0 + z1 * (a1 + z1 * a2))
+            })
+            .product()
+    }
+
+    fn gain_db(sections: &[Biquad], freq: f64) -> f64 {
+        20.0 * response(sections, freq).norm().log10()
+    }
+
+    /// Group delay in samples, from the slope of the unwrapped phase.
+    fn group_delay(sections: &[Biquad], freq: f64) -> f64 {
+        let step = 0.01;
+        let ratio = response(sections, freq + step) / response(sections, freq - step);
+        -ratio.arg() / (std::f64::consts::TAU * 2.0 * step / SAMPLE_RATE as f64)
+    }
+
+    fn gain_range(sections: &[Biquad], low: f64, high: f64) -> (f64, f64) {
+        (0..=200)
+            .map(|i| gain_db(sections, low + (high - low) * i as f64 / 200.0))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), db| {
+                (min.min(db), max.max(db))
+            })
+    }
+
+    #[test]
+    fn butterworth() {
+        for order in [4, 5] {
+            let sections = lowpass(order, Prototype::Butterworth);
+            assert!(gain_db(&sections, 0.0).abs() < 1e-3);
+            assert!((gain_db(&sections, 1000.0) + 3.0103).abs() < 1e-2);
+        }
+        // 6 dB per octave per order, plus the bilinear transform's warping.
+        assert!(gain_db(&lowpass(5, Prototype::Butterworth), 2000.0) < -38.0);
+    }
+
+    #[test]
+    fn chebyshev_i() {
+        let prototype = Prototype::ChebyshevI { ripple: 1.0 };
+        for order in [4, 5] {
+            let sections = lowpass(order, prototype);
+            let (min, max) = gain_range(&sections, 0.0, 1000.0);
+            assert!(min > -1.01 && max < 1e-3, "{min} {max}");
+            assert!((gain_db(&sections, 1000.0) + 1.0).abs() < 1e-2);
+        }
+        // Even orders start the passband at the bottom of the ripple.
+        assert!((gain_db(&lowpass(4, prototype), 0.0) + 1.0).abs() < 1e-2);
+        assert!(gain_db(&lowpass(5, prototype), 0.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn chebyshev_ii() {
+        let sections = lowpass(5, Prototype::ChebyshevII { attenuation: 40.0 });
+        assert!(gain_db(&sections, 0.0).abs() < 1e-3);
+        assert!((gain_db(&sections, 1000.0) + 40.0).abs() < 1e-2);
+        let (_, max) = gain_range(&sections, 1000.0, 4000.0);
+        assert!(max < -39.99, "{max}");
+    }
+
+    #[test]
+    fn bessel() {
+        let bessel = lowpass(5, Prototype::Bessel);
+        assert!(gain_db(&bessel, 0.0).abs() < 1e-3);
+        assert!((gain_db(&bessel, 1000.0) + 3.0103).abs() < 1e-2);
+
+        // The group delay is much flatter than a Butterworth filter's.
+        let flatness =
+            |sections: &[Biquad]| group_delay(sections, 500.0) / group_delay(sections, 100.0) - 1.0;
+        assert!(flatness(&bessel) < 0.05, "{}", flatness(&bessel));
+        let butterworth = lowpass(5, Prototype::Butterworth);
+        assert!(flatness(&butterworth) > 0.1);
+    }
+
+    #[test]
+    fn elliptic() {
+        let prototype = Prototype::Elliptic {
+            ripple: 1.0,
+            attenuation: 40.0,
+        };
+        let sections = lowpass(5, prototype);
+        let (min, max) = gain_range(&sections, 0.0, 1000.0);
+        assert!(min > -1.01 && max < 1e-3, "{min} {max}");
+        assert!((gain_db(&sections, 1000.0) + 1.0).abs() < 1e-2);
+        let (_, max) = gain_range(&sections, 1200.0, 4000.0);
+        assert!((-40.01..-39.9).contains(&max), "{max}");
+
+        // A lower order needs a wider transition band for the same ripples.
+        let (_, max) = gain_range(&lowpass(4, prototype), 1200.0, 4000.0);
+        assert!(max > -20.0);
+    }
+
+    #[test]
+    fn band_transforms() {
+        let highpass = design(
+            Passband::HighPass { cutoff: 1000.0 },
+            4,
+            Prototype::Butterworth,
+        );
+        assert_eq!(highpass.len(), 2);
+        assert!((gain_db(&highpass, 1000.0) + 3.0103).abs() < 1e-2);
+        assert!(gain_db(&highpass, 2000.0).abs() < 1e-2);
+        assert!(gain_db(&highpass, 100.0) < -80.0);
+
+        let (low_cutoff, high_cutoff) = (1000.0, 2000.0);
+        let center = (low_cutoff as f64 * high_cutoff as f64).sqrt();
+
+        let bandpass = design(
+            Passband::BandPass {
+                low_cutoff,
+                high_cutoff,
+            },
+            4,
+            Prototype::Butterworth,
+        );
+        assert_eq!(bandpass.len(), 4);
+        assert!((gain_db(&bandpass, 1000.0) + 3.0103).abs() < 1e-2);
+        assert!((gain_db(&bandpass, 2000.0) + 3.0103).abs() < 1e-2);
+        assert!(gain_db(&bandpass, center).abs() < 1e-2);
+
+        let bandreject = design(
+            Passband::BandReject {
+                low_cutoff,
+                high_cutoff,
+            },
+            4,
+            Prototype::Butterworth,
+        );
+        assert!((gain_db(&bandreject, 1000.0) + 3.0103).abs() < 1e-2);
+        assert!((gain_db(&bandreject, 2000.0) + 3.0103).abs() < 1e-2);
+        assert!(gain_db(&bandreject, center) < -60.0);
+        assert!(gain_db(&bandreject, 0.0).abs() < 1e-3);
+        assert!(gain_db(&bandreject, 4000.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn usable_as_filter() {
+        fn settle(filter: &mut impl Filter<Real>) -> Real {
+            let mut buffer = [1.0; 200];
+            filter.process_inplace(&mut buffer);
+            buffer[199]
+        }
+        let mut iir: Iir = Iir::new(lowpass(4, Prototype::Butterworth));
+        assert!((settle(&mut iir) - 1.0).abs() < 1e-3);
+    }
+}
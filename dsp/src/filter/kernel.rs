@@ -0,0 +1,579 @@
+//! Dot product kernels used by the FIR filters.
+//!
+//! On x86 and x86-64, the kernels for [`Real`] and [`IQ`] samples use explicit
+//! SIMD: SSE2, which every x86-64 CPU has, or AVX and FMA when the build
+//! enables them (e.g. with `-C target-cpu=native`). Other targets fall back to
+//! portable code with several independent accumulators, which the compiler
+//! can usually vectorize by itself.
+
+use std::ops;
+
+use crate::{
+    iq::IQ,
+    math::Real,
+    sample::{scalar_dot, Sample},
+};
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+use self::x86 as simd;
+
+#[cfg(not(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+)))]
+use self::portable as simd;
+
+/// Sample types that can be filtered by a [`Fir`](super::Fir) with taps of
+/// type `C`.
+///
+/// Every [`Sample`] can be filtered with real taps, through
+/// [`Sample::weighted_sum`]. Other tap types, such as complex or fixed-point
+/// taps, need an `impl`, where the default [`dot`](Self::dot) is a plain
+/// scalar loop.
+pub trait FirSample<C = Real>: Sample + ops::Mul<C, Output = Self>
+where
+    C: Copy,
+{
+    /// `sum(samples[k] * taps[k])` over the shorter of the two slices.
+    fn dot(taps: &[C], samples: &[Self]) -> Self {
+        scalar_dot(taps, samples)
+    }
+}
+
+impl<T: Sample> FirSample for T {
+    fn dot(taps: &[Real], samples: &[T]) -> T {
+        T::weighted_sum(taps, samples)
+    }
+}
+
+impl FirSample<IQ> for IQ {
+    fn dot(taps: &[IQ], samples: &[IQ]) -> IQ {
+        let len = taps.len().min(samples.len());
+        simd::complex_dot(&taps[..len], &samples[..len])
+    }
+}
+
+/// [`Sample::weighted_sum`] for real samples.
+pub(crate) fn real_dot(taps: &[Real], samples: &[Real]) -> Real {
+    let len = taps.len().min(samples.len());
+    simd::real_dot(&taps[..len], &samples[..len])
+}
+
+/// [`Sample::weighted_sum`] for complex samples.
+pub(crate) fn iq_dot(taps: &[Real], samples: &[IQ]) -> IQ {
+    let len = taps.len().min(samples.len());
+    simd::iq_dot(&taps[..len], &samples[..len])
+}
+
+/// Kernels over plain arrays, with `LANES` independent accumulators.
+///
+/// Like the SIMD kernels, these take slices of equal length.
+#[cfg(any(
+    test,
+    not(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    ))
+))]
+mod portable {
+    use super::scalar_dot;
+    use crate::{iq::IQ, math::Real};
+
+    const LANES: usize = 8;
+
+    fn sum(acc: [Real; LANES]) -> Real {
+        acc.iter().sum()
+    }
+
+    pub(super) fn real_dot(taps: &[Real], samples: &[Real]) -> Real {
+        let (taps, samples) = (taps.chunks_exact(LANES), samples.chunks_exact(LANES));
+        let rest = scalar_dot(taps.remainder(), samples.remainder());
+        let mut acc = [0.0; LANES];
+        for (taps, samples) in taps.zip(samples) {
+            for k in 0..LANES {
+                acc[k] += taps[k] * samples[k];
+            }
+        }
+        sum(acc) + rest
+    }
+
+    pub(super) fn iq_dot(taps: &[Real], samples: &[IQ]) -> IQ {
+        let (taps, samples) = (taps.chunks_exact(LANES), samples.chunks_exact(LANES));
+        let rest = scalar_dot(taps.remainder(), samples.remainder());
+        let mut acc_i = [0.0; LANES];
+        let mut acc_q = [0.0; LANES];
+        for (taps, samples) in taps.zip(samples) {
+            for k in 0..LANES {
+                acc_i[k] += taps[k] * samples[k].i;
+                acc_q[k] += taps[k] * samples[k].q;
+            }
+        }
+        IQ::new(sum(acc_i), sum(acc_q)) + rest
+    }
+
+    pub(super) fn complex_dot(taps: &[IQ], samples: &[IQ]) -> IQ {
+        let (taps, samples) = (taps.chunks_exact(LANES), samples.chunks_exact(LANES));
+        let rest = scalar_dot(taps.remainder(), samples.remainder());
+        // Accumulate the four partial products separately and combine them
+        // at the end, so each lane is a plain multiply-add.
+        let mut acc_ii = [0.0; LANES];
+        let mut acc_qq = [0.0; LANES];
+        let mut acc_iq = [0.0; LANES];
+        let mut acc_qi = [0.0; LANES];
+        for (taps, samples) in taps.zip(samples) {
+            for k in 0..LANES {
+                acc_ii[k] += samples[k].i * taps[k].i;
+                acc_qq[k] += samples[k].q * taps[k].q;
+                acc_iq[k] += samples[k].i * taps[k].q;
+                acc_qi[k] += samples[k].q * taps[k].i;
+            }
+        }
+        IQ::new(sum(acc_ii) - sum(acc_qq), sum(acc_iq) + sum(acc_qi)) + rest
+    }
+}
+
+/// Kernels using SSE2, or AVX and FMA when they are enabled at build time.
+///
+/// The kernels are written once against [`Register`], which is implemented
+/// for the widest register available for [`Real`]. Complex samples are read
+/// as interleaved `[i, q, i, q, ...]` values, relying on the `#[repr(C)]`
+/// layout of [`IQ`], so each register holds `WIDTH / 2` samples.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use super::scalar_dot;
+    use crate::{iq::IQ, math::Real};
+
+    /// Independent accumulators per kernel, to hide the latency of the adds.
+    const UNROLL: usize = 4;
+
+    /// Largest `Register::WIDTH`.
+    const MAX_WIDTH: usize = 8;
+
+    #[cfg(all(not(feature = "f64"), not(target_feature = "avx")))]
+    type V = __m128;
+    #[cfg(all(not(feature = "f64"), target_feature = "avx"))]
+    type V = __m256;
+    #[cfg(all(feature = "f64", not(target_feature = "avx")))]
+    type V = __m128d;
+    #[cfg(all(feature = "f64", target_feature = "avx"))]
+    type V = __m256d;
+
+    /// A SIMD register of `WIDTH` [`Real`]s.
+    ///
+    /// The methods are `unsafe` because the intrinsics need their target
+    /// features, which the `cfg` on the impl guarantees for the whole build,
+    /// and because the loads and stores need `WIDTH` values (`WIDTH / 2` for
+    /// [`load_pairs`](Self::load_pairs)) at `ptr`.
+    trait Register: Copy {
+        const WIDTH: usize;
+
+        unsafe fn zero() -> Self;
+
+        unsafe fn load(ptr: *const Real) -> Self;
+
+        /// Load `WIDTH / 2` values, repeating each one twice.
+        unsafe fn load_pairs(ptr: *const Real) -> Self;
+
+        /// Split interleaved complex values `[re, im, ...]` into
+        /// `[re, re, ...]` and `[im, im, ...]`.
+        unsafe fn split_complex(self) -> (Self, Self);
+
+        unsafe fn add(self, other: Self) -> Self;
+
+        /// `self + a * b`, fused when FMA is enabled.
+        unsafe fn mul_add(self, a: Self, b: Self) -> Self;
+
+        unsafe fn store(self, ptr: *mut Real);
+    }
+
+    #[cfg(all(not(feature = "f64"), not(target_feature = "avx")))]
+    impl Register for __m128 {
+        const WIDTH: usize = 4;
+
+        #[inline]
+        unsafe fn zero() -> Self {
+            _mm_setzero_ps()
+        }
+
+        #[inline]
+        unsafe fn load(ptr: *const Real) -> Self {
+            _mm_loadu_ps(ptr)
+        }
+
+        #[inline]
+        unsafe fn load_pairs(ptr: *const Real) -> Self {
+            let pair = _mm_castpd_ps(_mm_load_sd(ptr.cast()));
+            _mm_unpacklo_ps(pair, pair)
+        }
+
+        #[inline]
+        unsafe fn split_complex(self) -> (Self, Self) {
+            (
+                _mm_shuffle_ps::<0b10_10_00_00>(self, self),
+                _mm_shuffle_ps::<0b11_11_01_01>(self, self),
+            )
+        }
+
+        #[inline]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm_add_ps(self, other)
+        }
+
+        #[inline]
+        unsafe fn mul_add(self, a: Self, b: Self) -> Self {
+            #[cfg(target_feature = "fma")]
+            return _mm_fmadd_ps(a, b, self);
+            #[cfg(not(target_feature = "fma"))]
+            return _mm_add_ps(self, _mm_mul_ps(a, b));
+        }
+
+        #[inline]
+        unsafe fn store(self, ptr: *mut Real) {
+            _mm_storeu_ps(ptr, self)
+        }
+    }
+
+    #[cfg(all(not(feature = "f64"), target_feature = "avx"))]
+    impl Register for __m256 {
+        const WIDTH: usize = 8;
+
+        #[inline]
+        unsafe fn zero() -> Self {
+            _mm256_setzero_ps()
+        }
+
+        #[inline]
+        unsafe fn load(ptr: *const Real) -> Self {
+            _mm256_loadu_ps(ptr)
+        }
+
+        #[inline]
+        unsafe fn load_pairs(ptr: *const Real) -> Self {
+            let values = _mm_loadu_ps(ptr);
+            _mm256_set_m128(
+                _mm_unpackhi_ps(values, values),
+                _mm_unpacklo_ps(values, values),
+            )
+        }
+
+        #[inline]
+        unsafe fn split_complex(self) -> (Self, Self) {
+            (_mm256_moveldup_ps(self), _mm256_movehdup_ps(self))
+        }
+
+        #[inline]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm256_add_ps(self, other)
+        }
+
+        #[inline]
+        unsafe fn mul_add(self, a: Self, b: Self) -> Self {
+            #[cfg(target_feature = "fma")]
+            return _mm256_fmadd_ps(a, b, self);
+            #[cfg(not(target_feature = "fma"))]
+            return _mm256_add_ps(self, _mm256_mul_ps(a, b));
+        }
+
+        #[inline]
+        unsafe fn store(self, ptr: *mut Real) {
+            _mm256_storeu_ps(ptr, self)
+        }
+    }
+
+    #[cfg(all(feature = "f64", not(target_feature = "avx")))]
+    impl Register for __m128d {
+        const WIDTH: usize = 2;
+
+        #[inline]
+        unsafe fn zero() -> Self {
+            _mm_setzero_pd()
+        }
+
+        #[inline]
+        unsafe fn load(ptr: *const Real) -> Self {
+            _mm_loadu_pd(ptr)
+        }
+
+        #[inline]
+        unsafe fn load_pairs(ptr: *const Real) -> Self {
+            _mm_load1_pd(ptr)
+        }
+
+        #[inline]
+        unsafe fn split_complex(self) -> (Self, Self) {
+            (_mm_unpacklo_pd(self, self), _mm_unpackhi_pd(self, self))
+        }
+
+        #[inline]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm_add_pd(self, other)
+        }
+
+        #[inline]
+        unsafe fn mul_add(self, a: Self, b: Self) -> Self {
+            #[cfg(target_feature = "fma")]
+            return _mm_fmadd_pd(a, b, self);
+            #[cfg(not(target_feature = "fma"))]
+            return _mm_add_pd(self, _mm_mul_pd(a, b));
+        }
+
+        #[inline]
+        unsafe fn store(self, ptr: *mut Real) {
+            _mm_storeu_pd(ptr, self)
+        }
+    }
+
+    #[cfg(all(feature = "f64", target_feature = "avx"))]
+    impl Register for __m256d {
+        const WIDTH: usize = 4;
+
+        #[inline]
+        unsafe fn zero() -> Self {
+            _mm256_setzero_pd()
+        }
+
+        #[inline]
+        unsafe fn load(ptr: *const Real) -> Self {
+            _mm256_loadu_pd(ptr)
+        }
+
+        #[inline]
+        unsafe fn load_pairs(ptr: *const Real) -> Self {
+            let values = _mm_loadu_pd(ptr);
+            _mm256_set_m128d(
+                _mm_unpackhi_pd(values, values),
+                _mm_unpacklo_pd(values, values),
+            )
+        }
+
+        #[inline]
+        unsafe fn split_complex(self) -> (Self, Self) {
+            (_mm256_movedup_pd(self), _mm256_permute_pd::<0b1111>(self))
+        }
+
+        #[inline]
+        unsafe fn add(self, other: Self) -> Self {
+            _mm256_add_pd(self, other)
+        }
+
+        #[inline]
+        unsafe fn mul_add(self, a: Self, b: Self) -> Self {
+            #[cfg(target_feature = "fma")]
+            return _mm256_fmadd_pd(a, b, self);
+            #[cfg(not(target_feature = "fma"))]
+            return _mm256_add_pd(self, _mm256_mul_pd(a, b));
+        }
+
+        #[inline]
+        unsafe fn store(self, ptr: *mut Real) {
+            _mm256_storeu_pd(ptr, self)
+        }
+    }
+
+    /// Add the accumulators together and return the lanes of the result.
+    unsafe fn reduce(acc: [V; UNROLL]) -> [Real; MAX_WIDTH] {
+        let sum = acc[1..].iter().fold(acc[0], |sum, &x| sum.add(x));
+        let mut lanes = [0.0; MAX_WIDTH];
+        sum.store(lanes.as_mut_ptr());
+        lanes
+    }
+
+    /// Sums of the even and odd lanes, i.e. of the real and imaginary parts.
+    fn sum_complex(lanes: &[Real]) -> (Real, Real) {
+        lanes[..V::WIDTH]
+            .chunks_exact(2)
+            .fold((0.0, 0.0), |(re, im), pair| (re + pair[0], im + pair[1]))
+    }
+
+    fn as_reals(samples: &[IQ]) -> &[Real] {
+        // SAFETY: `IQ` is `#[repr(C)]` with two `Real` fields, so a slice of
+        // them is a slice of twice as many `Real`s.
+        unsafe { std::slice::from_raw_parts(samples.as_ptr().cast(), 2 * samples.len()) }
+    }
+
+    pub(super) fn real_dot(taps: &[Real], samples: &[Real]) -> Real {
+        let step = UNROLL * V::WIDTH;
+        let whole = taps.len() - taps.len() % step;
+        // SAFETY: every load reads `V::WIDTH` values starting at `offset`, and
+        // `offset + V::WIDTH <= whole`, which is within both slices.
+        let lanes = unsafe {
+            let mut acc = [V::zero(); UNROLL];
+            for start in (0..whole).step_by(step) {
+                for (k, acc) in acc.iter_mut().enumerate() {
+                    let offset = start + k * V::WIDTH;
+                    let tap = V::load(taps.as_ptr().add(offset));
+                    let sample = V::load(samples.as_ptr().add(offset));
+                    *acc = acc.mul_add(tap, sample);
+                }
+            }
+            reduce(acc)
+        };
+        lanes[..V::WIDTH].iter().sum::<Real>() + scalar_dot(&taps[whole..], &samples[whole..])
+    }
+
+    pub(super) fn iq_dot(taps: &[Real], samples: &[IQ]) -> IQ {
+        let per_register = V::WIDTH / 2;
+        let step = UNROLL * per_register;
+        let whole = taps.len() - taps.len() % step;
+        let values = as_reals(samples);
+        // SAFETY: each register covers `per_register` taps and samples
+        // starting at `offset`, and `offset + per_register <= whole`.
+        let lanes = unsafe {
+            let mut acc = [V::zero(); UNROLL];
+            for start in (0..whole).step_by(step) {
+                for (k, acc) in acc.iter_mut().enumerate() {
+                    let offset = start + k * per_register;
+                    let tap = V::load_pairs(taps.as_ptr().add(offset));
+                    let sample = V::load(values.as_ptr().add(2 * offset));
+                    *acc = acc.mul_add(tap, sample);
+                }
+            }
+            reduce(acc)
+        };
+        let (i, q) = sum_complex(&lanes);
+        IQ::new(i, q) + scalar_dot(&taps[whole..], &samples[whole..])
+    }
+
+    pub(super) fn complex_dot(taps: &[IQ], samples: &[IQ]) -> IQ {
+        let per_register = V::WIDTH / 2;
+        let step = UNROLL * per_register;
+        let whole = taps.len() - taps.len() % step;
+        let (tap_values, sample_values) = (as_reals(taps), as_reals(samples));
+        // SAFETY: as in `iq_dot`, but reading `2 * per_register` values of
+        // both slices at `2 * offset`.
+        let (by_re, by_im) = unsafe {
+            // `by_re` accumulates `[i * re, q * re, ...]` and `by_im`
+            // accumulates `[i * im, q * im, ...]`.
+            let mut by_re = [V::zero(); UNROLL];
+            let mut by_im = [V::zero(); UNROLL];
+            for start in (0..whole).step_by(step) {
+                for k in 0..UNROLL {
+                    let offset = 2 * (start + k * per_register);
+                    let (re, im) = V::load(tap_values.as_ptr().add(offset)).split_complex();
+                    let sample = V::load(sample_values.as_ptr().add(offset));
+                    by_re[k] = by_re[k].mul_add(re, sample);
+                    by_im[k] = by_im[k].mul_add(im, sample);
+                }
+            }
+            (reduce(by_re), reduce(by_im))
+        };
+        let (i_re, q_re) = sum_complex(&by_re);
+        let (i_im, q_im) = sum_complex(&by_im);
+        IQ::new(i_re - q_im, q_re + i_im) + scalar_dot(&taps[whole..], &samples[whole..])
+    }
+}
+
+/// Delay line that keeps the most recent samples contiguous, newest first.
+///
+/// Each sample is stored twice, `len` apart, so the window never wraps and
+/// can be passed straight to [`FirSample::dot`].
+#[derive(Clone)]
+pub(crate) struct DelayLine<T> {
+    buffer: Box<[T]>,
+    position: usize,
+}
+
+impl<T: Sample> DelayLine<T> {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![T::ZERO; 2 * len].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buffer.len() / 2
+    }
+
+    pub(crate) fn push(&mut self, sample: T) {
+        let len = self.len();
+        if len == 0 {
+            return;
+        }
+        // Walk backwards so that the newest sample comes first.
+        self.position = self.position.checked_sub(1).unwrap_or(len - 1);
+        self.buffer[self.position] = sample;
+        self.buffer[self.position + len] = sample;
+    }
+
+    /// The last `len` samples, newest first.
+    pub(crate) fn window(&self) -> &[T] {
+        &self.buffer[self.position..self.position + self.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(len: usize, seed: usize) -> Vec<Real> {
+        (0..len)
+            .map(|n| ((n * 7919 + seed * 104729) % 2003) as Real / 1001.5 - 1.0)
+            .collect()
+    }
+
+    fn complex(len: usize, seed: usize) -> Vec<IQ> {
+        values(2 * len, seed)
+            .chunks_exact(2)
+            .map(|pair| IQ::new(pair[0], pair[1]))
+            .collect()
+    }
+
+    fn assert_close(actual: IQ, expected: IQ) {
+        assert!(
+            (actual - expected).magnitude() < 1e-4,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    /// Lengths around every multiple of the widest register times the unroll
+    /// factor, so that both the vector loop and the scalar tail are covered.
+    fn lengths() -> impl Iterator<Item = usize> {
+        0..=70
+    }
+
+    #[test]
+    fn real_kernels_match_scalar() {
+        for len in lengths() {
+            let (taps, samples) = (values(len, 1), values(len + 3, 2));
+            let expected = scalar_dot(&taps, &samples);
+            for actual in [
+                real_dot(&taps, &samples),
+                portable::real_dot(&taps, &samples[..len]),
+            ] {
+                assert_close(actual.into(), expected.into());
+            }
+        }
+    }
+
+    #[test]
+    fn iq_kernels_match_scalar() {
+        for len in lengths() {
+            let (taps, samples) = (values(len + 3, 1), complex(len, 2));
+            let expected = scalar_dot(&taps, &samples);
+            assert_close(iq_dot(&taps, &samples), expected);
+            assert_close(portable::iq_dot(&taps[..len], &samples), expected);
+        }
+    }
+
+    #[test]
+    fn complex_kernels_match_scalar() {
+        for len in lengths() {
+            let (taps, samples) = (complex(len, 1), complex(len, 2));
+            let expected = scalar_dot(&taps, &samples);
+            assert_close(IQ::dot(&taps, &samples), expected);
+            assert_close(portable::complex_dot(&taps, &samples), expected);
+        }
+    }
+}
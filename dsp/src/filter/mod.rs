@@ -0,0 +1,688 @@
+use crate::{
+    fft::RealFft,
+    fixed::{quantize, FixedPoint},
+    iq::IQ,
+    math::{bessel_i0, cos, rc, sinc, Real, PI, TAU},
+    sample::Sample,
+};
+
+use self::{
+    kernel::{DelayLine, FirSample},
+    response::FrequencyResponse,
+};
+
+pub mod adaptive;
+pub mod blocks;
+pub mod fast;
+pub mod hilbert;
+pub mod iir;
+pub mod kernel;
+pub mod remez;
+pub mod response;
+pub mod taps;
+pub mod xlating;
+
+/// Finite impulse response filter.
+///
+/// The taps are real by default, but may be any type that the samples can be
+/// multiplied by; see [`ComplexFir`] and [`FirSample`].
+///
+/// The taps are the impulse response, in the same order as designs from
+/// SciPy, Octave or GNU Radio: `taps[0]` is applied to the most recent
+/// sample.
+#[derive(Clone)]
+pub struct Fir<T = Real, C = Real> {
+    taps: Box<[C]>,
+    delay_line: DelayLine<T>,
+}
+
+/// FIR filter with complex taps, operating on IQ samples.
+///
+/// Unlike filters with real taps, the response does not have to be symmetric
+/// around zero frequency.
+pub type ComplexFir = Fir<IQ, IQ>;
+
+impl<T: FirSample> Fir<T> {
+    pub fn linear_interp(period: usize) -> Self {
+        let half_width = period as isize - 1;
+        let num_taps = half_width as usize * 2 + 1;
+        let taps: Box<[Real]> = (-half_width..=half_width)
+            .map(|t| 1.0 - (t.abs() as Real) / (period as Real))
+            .collect();
+        assert_eq!(taps.len(), num_taps);
+
+        Self::new(taps)
+    }
+
+    pub fn raised_cosine(num_taps: usize, rolloff: Real, sps: Real) -> Self {
+        // Ensure num_taps is odd
+        let num_taps = num_taps | 1;
+        let half_width = num_taps as isize / 2;
+        let taps: Box<[Real]> = (-half_width..=half_width)
+            .map(|t| rc(t as Real, rolloff, sps))
+            .collect();
+        assert_eq!(taps.len(), num_taps);
+
+        Self::new(taps)
+    }
+}
+
+impl ComplexFir {
+    /// Shift a real (lowpass) prototype up in frequency by `center_freq`.
+    ///
+    /// The taps are modulated by a complex exponential referenced to the
+    /// center tap, so a lowpass prototype with cutoff `B / 2` becomes a
+    /// band-pass from `center_freq - B / 2` to `center_freq + B / 2` which only
+    /// passes that band on one side of zero frequency, with the same delay as
+    /// the prototype.
+    pub fn frequency_shifted(taps: &[Real], sample_rate: Real, center_freq: Real) -> Self {
+        let center = 0.5 * (taps.len() as Real - 1.0);
+        let taps: Box<[IQ]> = taps
+            .iter()
+            .enumerate()
+            .map(|(i, &tap)| {
+                IQ::new_polar(TAU * center_freq / sample_rate * (i as Real - center), tap)
+            })
+            .collect();
+        Self::new(taps)
+    }
+}
+
+impl<T, C> Fir<T, C>
+where
+    T: FirSample<C>,
+    C: Copy,
+{
+    pub fn new(taps: impl Into<Box<[C]>>) -> Self {
+        let taps = taps.into();
+        Self {
+            delay_line: DelayLine::new(taps.len()),
+            taps,
+        }
+    }
+
+    /// The impulse response of this filter; `taps[0]` is applied to the most
+    /// recent sample.
+    pub fn taps(&self) -> &[C] {
+        &self.taps
+    }
+
+    /// Replace the taps with ones of the same length, keeping the samples in
+    /// the delay line.
+    pub(crate) fn set_taps(&mut self, taps: &[C]) {
+        self.taps.copy_from_slice(taps);
+    }
+
+    /// Evaluate the frequency response of this filter at each of `freqs`.
+    ///
+    /// See [`FrequencyResponse::linear_grid`] for a convenient grid.
+    pub fn response(
+        &self,
+        sample_rate: Real,
+        freqs: impl IntoIterator<Item = Real>,
+    ) -> FrequencyResponse
+    where
+        C: Into<IQ>,
+    {
+        FrequencyResponse::evaluate(&self.taps, sample_rate, freqs)
+    }
+
+    pub fn process_sample(&mut self, sample: T) -> T {
+        self.delay_line.push(sample);
+        T::dot(&self.taps, self.delay_line.window())
+    }
+
+    pub fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+
+    /// Process all of `buffer`, returning only the last output.
+    pub fn decimate(&mut self, buffer: &[T]) -> T {
+        let (&last, rest) = buffer.split_last().unwrap();
+        // Only the last output is kept, so the others needn't be computed.
+        for &sample in rest {
+            self.delay_line.push(sample);
+        }
+        self.process_sample(last)
+    }
+}
+
+/// A filter that processes one sample at a time.
+///
+/// Implemented by [`Fir`], [`Iir`](iir::Iir) and the filters in [`blocks`],
+/// so that they can be swapped for one another, e.g. as the loop filter of a
+/// [`Costas`](crate::pll::Costas) loop. The resamplers in
+/// [`resample`](crate::resample) and [`FreqXlatingFir`](xlating::FreqXlatingFir)
+/// split a filter's taps into polyphase branches, so those take a [`Fir`]
+/// specifically.
+pub trait Filter<T: Copy> {
+    fn process_sample(&mut self, sample: T) -> T;
+
+    fn process_inplace(&mut self, buffer: &mut [T]) {
+        for slot in buffer {
+            *slot = self.process_sample(*slot);
+        }
+    }
+}
+
+impl<T, C> Filter<T> for Fir<T, C>
+where
+    T: FirSample<C>,
+    C: Copy,
+{
+    fn process_sample(&mut self, sample: T) -> T {
+        Fir::process_sample(self, sample)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowMethod {
+    pub gain: Real,
+    pub sample_rate: Real,
+    pub passband: Passband,
+    pub transition_width: Option<Real>,
+    /// Minimum stopband attenuation, in dB.
+    ///
+    /// When given along with `transition_width`, the number of taps is
+    /// estimated using Kaiser's formula instead of the default rule of thumb.
+    /// Pair this with [`Window::kaiser`] to also derive the window shape from
+    /// the same spec.
+    pub attenuation: Option<Real>,
+    pub num_taps: Option<usize>,
+    pub window: Window,
+}
+
+impl WindowMethod {
+    pub fn num_taps(&self) -> usize {
+        let mut num_taps = self.num_taps.unwrap_or(0);
+        if let Some(transition_width) = self.transition_width {
+            let estimate = match self.attenuation {
+                Some(attenuation) => {
+                    let transition_angle = TAU * transition_width / self.sample_rate;
+                    (attenuation - 7.95) / (2.285 * transition_angle) + 1.0
+                }
+                None => 4.0 * (self.sample_rate / transition_width),
+            };
+            num_taps = num_taps.max(estimate.ceil() as usize);
+        }
+        assert!(num_taps > 0);
+        // Ensure num_taps is odd
+        num_taps | 1
+    }
+
+    pub fn taps(&self) -> Box<[Real]> {
+        let num_taps = self.num_taps();
+        // Sample both functions over the closed interval so that the taps are
+        // symmetric around the center tap (linear phase).
+        let span = (num_taps - 1).max(1) as Real;
+
+        (0..num_taps)
+            .map(|x| {
+                self.gain
+                    * self.passband.sample(x as Real, span, self.sample_rate)
+                    * self.window.sample(x as Real, span)
+            })
+            .collect()
+    }
+
+    pub fn build<T: FirSample>(&self) -> Fir<T> {
+        Fir::new(self.taps())
+    }
+
+    /// Taps quantized to a fixed-point type.
+    ///
+    /// Taps outside `[-1, 1)` saturate, which is only possible with a `gain`
+    /// above one.
+    pub fn quantized_taps<C: FixedPoint>(&self) -> Box<[C]> {
+        quantize(&self.taps())
+    }
+
+    /// Build a filter with taps quantized to a fixed-point type.
+    ///
+    /// See [`quantized_taps`](Self::quantized_taps).
+    pub fn build_quantized<T: FirSample<C>, C: FixedPoint>(&self) -> Fir<T, C> {
+        Fir::new(self.quantized_taps())
+    }
+
+    /// Evaluate the frequency response of this design at `num_points` equally
+    /// spaced frequencies from zero to Nyquist.
+    pub fn response(&self, num_points: usize) -> FrequencyResponse {
+        let freqs = FrequencyResponse::linear_grid(0.0, 0.5 * self.sample_rate, num_points);
+        FrequencyResponse::evaluate(&self.taps(), self.sample_rate, freqs)
+    }
+
+    /// Build this design shifted up in frequency by `center_freq`.
+    ///
+    /// See [`ComplexFir::frequency_shifted`].
+    pub fn build_shifted(&self, center_freq: Real) -> ComplexFir {
+        ComplexFir::frequency_shifted(&self.taps(), self.sample_rate, center_freq)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Passband {
+    LowPass { cutoff: Real },
+    HighPass { cutoff: Real },
+    BandPass { low_cutoff: Real, high_cutoff: Real },
+    BandReject { low_cutoff: Real, high_cutoff: Real },
+}
+
+impl Passband {
+    pub fn centered_band_pass(center_freq: Real, bandwidth: Real) -> Self {
+        Self::BandPass {
+            low_cutoff: center_freq - 0.5 * bandwidth,
+            high_cutoff: center_freq + 0.5 * bandwidth,
+        }
+    }
+
+    pub fn centered_band_reject(center_freq: Real, bandwidth: Real) -> Self {
+        Self::BandReject {
+            low_cutoff: center_freq - 0.5 * bandwidth,
+            high_cutoff: center_freq + 0.5 * bandwidth,
+        }
+    }
+
+    pub fn sample(&self, x: Real, n: Real, sample_rate: Real) -> Real {
+        let xc = x - 0.5 * n;
+
+        match self {
+            Self::LowPass { cutoff } => {
+                let bw = 2.0 * cutoff / sample_rate;
+                bw * sinc(bw * xc)
+            }
+            Self::HighPass { cutoff } => {
+                let bw = 2.0 * cutoff / sample_rate;
+                sinc(xc) - bw * sinc(bw * xc)
+            }
+            Self::BandPass {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let low_bw = 2.0 * low_cutoff / sample_rate;
+                let high_bw = 2.0 * high_cutoff / sample_rate;
+                high_bw * sinc(high_bw * xc) - low_bw * sinc(low_bw * xc)
+            }
+            Self::BandReject {
+                low_cutoff,
+                high_cutoff,
+            } => {
+                let low_bw = 2.0 * low_cutoff / sample_rate;
+                let high_bw = 2.0 * high_cutoff / sample_rate;
+                sinc(xc) - high_bw * sinc(high_bw * xc) + low_bw * sinc(low_bw * xc)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Window {
+    Rectangular,
+    Bartlett,
+    Welch,
+    Gaussian { std_dev: Real },
+    Tukey { param: Real },
+    Kaiser { param: Real },
+    Exponential { time_constant: Real },
+    CosineSum2([Real; 2]),
+    CosineSum3([Real; 3]),
+    CosineSum4([Real; 4]),
+    CosineSum5([Real; 5]),
+}
+
+impl Window {
+    pub const HANN: Self = Self::CosineSum2([0.5, 0.5]);
+    pub const HAMMING: Self = Self::CosineSum2([0.54, 0.46]);
+    pub const BLACKMAN: Self = Self::CosineSum3([0.42, 0.5, 0.08]);
+    pub const NUTTALL_CFD: Self = Self::CosineSum4([0.355768, 0.487396, 0.144232, 0.012604]);
+    pub const BLACKMAN_NUTTALL: Self =
+        Self::CosineSum4([0.3635819, 0.4891775, 0.1365995, 0.0106411]);
+    pub const BLACKMAN_HARRIS: Self = Self::CosineSum4([0.35875, 0.48829, 0.14128, 0.01168]);
+    pub const FLAT_TOP: Self = Self::CosineSum5([
+        0.21557895,
+        0.41663158,
+        0.277263158,
+        0.083578947,
+        0.006947368,
+    ]);
+
+    /// A Kaiser window meeting the given stopband attenuation, in dB.
+    ///
+    /// The shape parameter is chosen with Kaiser's empirical formula for beta.
+    /// Note that `Kaiser { param }` takes `param = beta / PI`.
+    pub fn kaiser(attenuation: Real) -> Self {
+        let beta = if attenuation > 50.0 {
+            0.1102 * (attenuation - 8.7)
+        } else if attenuation >= 21.0 {
+            0.5842 * (attenuation - 21.0).powf(0.4) + 0.07886 * (attenuation - 21.0)
+        } else {
+            0.0
+        };
+        Self::Kaiser { param: beta / PI }
+    }
+
+    pub fn sample(&self, x: Real, n: Real) -> Real {
+        // "Normalized X", 0.0..1.0
+        let xn = x / n;
+        // "Centered X", -1.0..1.0
+        let xc = 2.0 * xn - 1.0;
+
+        match *self {
+            Self::Rectangular => 1.0,
+            Self::Bartlett => 1.0 - xc.abs(),
+            Self::Welch => 1.0 - xc.powi(2),
+            Self::Gaussian { std_dev } => (-0.5 * (xc / std_dev).powi(2)).exp(),
+            Self::Tukey { param } => {
+                if xc.abs() > param {
+                    0.5 * (1.0 - cos(PI / param * xc))
+                } else {
+                    1.0
+                }
+            }
+            Self::Kaiser { param } => {
+                let beta = PI * param;
+                bessel_i0(beta * (1.0 - xc.powi(2)).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+            Self::Exponential { time_constant } => (-(xn - 0.5).abs() / time_constant).exp(),
+            Self::CosineSum2([a, b]) => a - b * cos(TAU * xn),
+            Self::CosineSum3([a, b, c]) => a - b * cos(TAU * xn) + c * cos(2.0 * TAU * xn),
+            Self::CosineSum4([a, b, c, d]) => {
+                a - b * cos(TAU * xn) + c * cos(2.0 * TAU * xn) - d * cos(3.0 * TAU * xn)
+            }
+            Self::CosineSum5([a, b, c, d, e]) => {
+                a - b * cos(TAU * xn) + c * cos(2.0 * TAU * xn) - d * cos(3.0 * TAU * xn)
+                    + e * cos(4.0 * TAU * xn)
+            }
+        }
+    }
+
+    /// Generate a window of `len` points for spectral analysis, scaled so
+    /// that its peak is 1.
+    ///
+    /// The window is periodic: it is sampled over `len + 1` points and the last
+    /// one is dropped, so that consecutive windows tile evenly and a DFT of
+    /// `len` points sees exactly the window shape that the metrics below
+    /// describe. (Filter designs like [`WindowMethod`] instead sample the
+    /// symmetric window over `len` points.)
+    pub fn generate(&self, len: usize) -> Box<[Real]> {
+        assert!(len > 0);
+        let mut window: Box<[Real]> = (0..len)
+            .map(|x| self.sample(x as Real, len as Real))
+            .collect();
+        let peak = window.iter().fold(0.0, |peak: Real, &w| peak.max(w.abs()));
+        if peak > 0.0 {
+            for w in &mut window[..] {
+                *w /= peak;
+            }
+        }
+        window
+    }
+
+    /// Mean of the window of `len` points relative to its peak, which is the
+    /// factor by which it scales the amplitude of a tone in the center of a
+    /// DFT bin.
+    pub fn coherent_gain(&self, len: usize) -> Real {
+        let window = self.generate(len);
+        window.iter().sum::<Real>() / len as Real
+    }
+
+    /// Equivalent noise bandwidth of the window of `len` points, in DFT bins.
+    ///
+    /// This is the width of an ideal rectangular filter that would pass the
+    /// same noise power as one bin of a DFT using this window, and is 1 for
+    /// the rectangular window.
+    ///
+    /// ```
+    /// use k9api_dsp::filter::Window;
+    ///
+    /// let hann = Window::HANN;
+    /// assert!((hann.enbw(1024) - 1.5).abs() < 1e-3);
+    /// assert!((hann.coherent_gain(1024) - 0.5).abs() < 1e-3);
+    /// assert!((hann.scalloping_loss(1024) - 1.42).abs() < 0.01);
+    /// assert!((hann.highest_sidelobe(1024) + 31.5).abs() < 0.1);
+    /// assert!((hann.sidelobe_falloff(1024) + 18.0).abs() < 0.5);
+    /// ```
+    pub fn enbw(&self, len: usize) -> Real {
+        let window = self.generate(len);
+        let sum: Real = window.iter().sum();
+        let energy: Real = window.iter().map(|w| w * w).sum();
+        len as Real * energy / (sum * sum)
+    }
+
+    /// Processing loss of the window of `len` points, in dB: the reduction
+    /// in signal-to-noise ratio of a tone in the center of a bin compared to
+    /// the rectangular window, which is the equivalent noise bandwidth in dB.
+    pub fn processing_loss(&self, len: usize) -> Real {
+        10.0 * self.enbw(len).log10()
+    }
+
+    /// Scalloping loss of the window of `len` points, in dB: how much weaker
+    /// a tone exactly halfway between two bins appears than one in the center
+    /// of a bin.
+    pub fn scalloping_loss(&self, len: usize) -> Real {
+        let window = self.generate(len);
+        let loss = window_magnitude(&window, 0.5) / window_magnitude(&window, 0.0);
+        (-20.0 * loss.log10()) as Real
+    }
+
+    /// Level of the highest sidelobe of the window of `len` points, in dB
+    /// relative to the main lobe (so it is negative).
+    ///
+    /// The main lobe extends to the first minimum of the response below -6 dB,
+    /// which is found on a grid of 1/16 bin.
+    pub fn highest_sidelobe(&self, len: usize) -> Real {
+        const OVERSAMPLE: usize = 16;
+
+        let window = self.generate(len);
+        let mut padded = vec![0.0; len * OVERSAMPLE];
+        padded[..len].copy_from_slice(&window);
+        let fft = RealFft::new(padded.len());
+        let mut spectrum = vec![IQ::ZERO; fft.num_bins()];
+        fft.forward(&padded, &mut spectrum);
+        let magnitudes: Vec<Real> = spectrum.iter().map(|x| x.magnitude()).collect();
+
+        // Some windows, like the flat top, ripple across their main lobe, so
+        // only count minima once the response has fallen by half.
+        let main_lobe_end = magnitudes
+            .windows(2)
+            .position(|pair| pair[1] > pair[0] && pair[0] < 0.5 * magnitudes[0])
+            .unwrap_or(magnitudes.len());
+        let sidelobe = magnitudes[main_lobe_end..]
+            .iter()
+            .fold(0.0, |peak: Real, &m| peak.max(m));
+        20.0 * (sidelobe / magnitudes[0]).log10()
+    }
+
+    /// Rate at which the sidelobes of the window of `len` points fall off
+    /// with frequency, in dB per octave (so it is negative).
+    ///
+    /// Measured between the sidelobe peaks around `len / 16` and `len / 8`
+    /// bins from the main lobe. The result approaches the asymptotic rate for
+    /// long windows: -6 dB per octave for windows with a step at their ends,
+    /// such as the rectangular and Hamming windows, and -18 dB per octave for
+    /// ones with a continuous value and slope, such as the Hann window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is less than 128, which leaves too few sidelobes to
+    /// measure between.
+    pub fn sidelobe_falloff(&self, len: usize) -> Real {
+        assert!(len >= 128, "window too short to measure sidelobe falloff");
+        let window = self.generate(len);
+
+        // The largest response over the span of one sidelobe, wherever its
+        // peak falls.
+        let sidelobe_peak = |center: usize| {
+            (0..=32)
+                .map(|step| window_magnitude(&window, center as f64 - 0.5 + step as f64 / 32.0))
+                .fold(0.0, f64::max)
+        };
+        let near = sidelobe_peak(len / 16);
+        let far = sidelobe_peak(len / 8);
+        (20.0 * (far / near).log10()) as Real
+    }
+}
+
+/// Magnitude of the DTFT of `window` at `bins` DFT bins from DC, evaluated in
+/// double precision so that deep sidelobes aren't lost in rounding error.
+fn window_magnitude(window: &[Real], bins: f64) -> f64 {
+    let step = std::f64::consts::TAU * bins / window.len() as f64;
+    let (re, im) = window
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, &w)| {
+            let angle = step * n as f64;
+            (re + w as f64 * angle.cos(), im - w as f64 * angle.sin())
+        });
+    re.hypot(im)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed::Q15;
+
+    const SAMPLE_RATE: Real = 8000.0;
+
+    /// Frequency ranges, as `(low, high)` in Hz.
+    type Bands = &'static [(Real, Real)];
+
+    fn kaiser(passband: Passband, attenuation: Real) -> WindowMethod {
+        WindowMethod {
+            gain: 1.0,
+            sample_rate: SAMPLE_RATE,
+            passband,
+            transition_width: Some(400.0),
+            attenuation: Some(attenuation),
+            num_taps: None,
+            window: Window::kaiser(attenuation),
+        }
+    }
+
+    #[test]
+    fn window_method_passbands() {
+        let attenuation = 60.0;
+        // (passband, pass bands, stop bands), leaving out the 400 Hz
+        // transitions around each cutoff.
+        let specs: [(Passband, Bands, Bands); 4] = [
+            (
+                Passband::LowPass { cutoff: 1000.0 },
+                &[(0.0, 800.0)],
+                &[(1200.0, 4000.0)],
+            ),
+            (
+                Passband::HighPass { cutoff: 1000.0 },
+                &[(1200.0, 4000.0)],
+                &[(0.0, 800.0)],
+            ),
+            (
+                Passband::BandPass {
+                    low_cutoff: 1000.0,
+                    high_cutoff: 2000.0,
+                },
+                &[(1200.0, 1800.0)],
+                &[(0.0, 800.0), (2200.0, 4000.0)],
+            ),
+            (
+                Passband::BandReject {
+                    low_cutoff: 1000.0,
+                    high_cutoff: 2000.0,
+                },
+                &[(0.0, 800.0), (2200.0, 4000.0)],
+                &[(1200.0, 1800.0)],
+            ),
+        ];
+        for (passband, pass, stop) in specs {
+            let design = kaiser(passband, attenuation);
+            let taps = design.taps();
+            // Linear phase.
+            for (a, b) in taps.iter().zip(taps.iter().rev()) {
+                assert!((a - b).abs() <= Real::EPSILON);
+            }
+            let response = design.response(4001);
+            for &(low, high) in pass {
+                // Kaiser's formulas give about the same ripple in both bands.
+                let (min, max) = (response.min_db(low, high), response.max_db(low, high));
+                assert!(min > -0.02 && max < 0.02, "{passband:?}: {min} {max}");
+            }
+            for &(low, high) in stop {
+                // Band-pass and band-reject designs combine two cutoffs,
+                // whose ripples can add up to twice the amplitude.
+                let max = response.max_db(low, high);
+                assert!(max < -attenuation + 6.0, "{passband:?}: {max}");
+            }
+        }
+    }
+
+    #[test]
+    fn window_method_samples_closed_interval() {
+        // The window and the passband are both centered on the middle tap,
+        // with the window reaching its end values at the first and last taps.
+        let design = |passband| WindowMethod {
+            gain: 1.0,
+            sample_rate: SAMPLE_RATE,
+            passband,
+            transition_width: None,
+            attenuation: None,
+            num_taps: Some(7),
+            window: Window::HAMMING,
+        };
+        let low_pass = design(Passband::LowPass { cutoff: 1000.0 }).taps();
+        let high_pass = design(Passband::HighPass { cutoff: 1000.0 }).taps();
+        let expected = [0.006002109, 0.04933803, 0.1733109, 0.25];
+        for (n, &tap) in expected.iter().enumerate() {
+            for k in [n, 6 - n] {
+                assert!((low_pass[k] - tap).abs() < 1e-6, "{k}: {}", low_pass[k]);
+                let high = if k == 3 { 1.0 - tap } else { -tap };
+                assert!((high_pass[k] - high).abs() < 1e-6, "{k}: {}", high_pass[k]);
+            }
+        }
+    }
+
+    #[test]
+    fn shifted_design_passes_one_side() {
+        let design = kaiser(Passband::LowPass { cutoff: 500.0 }, 60.0);
+        let fir = design.build_shifted(2000.0);
+        let freqs = FrequencyResponse::linear_grid(-4000.0, 4000.0, 801);
+        let response = fir.response(SAMPLE_RATE, freqs);
+        assert!(response.ripple_db(1700.0, 2300.0) < 0.04);
+        assert!(response.max_db(1700.0, 2300.0).abs() < 0.02);
+        assert!(response.max_db(-4000.0, 1300.0) < -59.0);
+        assert!(response.max_db(2700.0, 4000.0) < -59.0);
+        // The delay is still that of the prototype.
+        let center = (fir.taps().len() - 1) as Real / 2.0;
+        for point in response.band(1700.0, 2300.0) {
+            assert!((point.group_delay - center).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn quantized_taps_round_to_nearest() {
+        let design = kaiser(Passband::LowPass { cutoff: 1000.0 }, 60.0);
+        let quantized: Box<[Q15]> = design.quantized_taps();
+        for (&tap, q) in design.taps().iter().zip(&quantized[..]) {
+            assert!((q.to_real() - tap).abs() <= 0.5 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn linear_interp_taps() {
+        let fir: Fir = Fir::linear_interp(4);
+        assert_eq!(fir.taps(), [0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn raised_cosine_has_no_intersymbol_interference() {
+        let sps = 8.0;
+        let fir: Fir = Fir::raised_cosine(81, 0.35, sps);
+        let taps = fir.taps();
+        let center = taps.len() / 2;
+        assert!((taps[center] * sps - 1.0).abs() < 1e-6);
+        for k in (center % 8..taps.len()).step_by(8) {
+            if k != center {
+                assert!(taps[k].abs() < 1e-6, "{k}");
+            }
+        }
+    }
+}
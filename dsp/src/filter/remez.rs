@@ -0,0 +1,509 @@
+//! Equiripple FIR design using the Parks-McClellan (Remez exchange) algorithm.
+
+use crate::{
+    filter::{kernel::FirSample, Fir, Passband},
+    math::Real,
+};
+
+/// Number of grid points per coefficient used to search for extremal
+/// frequencies.
+const GRID_DENSITY: usize = 16;
+
+const MAX_ITERATIONS: usize = 100;
+
+/// How far the error of a design may exceed the equiripple deviation,
+/// relative to the deviation.
+const EQUIRIPPLE_TOLERANCE: f64 = 1e-2;
+
+/// Error small enough to count as meeting the spec exactly.
+const NEGLIGIBLE_ERROR: f64 = 1e-6;
+
+/// A frequency band with a constant desired gain.
+#[derive(Debug, Clone, Copy)]
+pub struct Band {
+    /// Lower band edge, in Hz.
+    pub low: Real,
+    /// Upper band edge, in Hz.
+    pub high: Real,
+    /// Desired gain within the band.
+    pub gain: Real,
+    /// Relative weight of the approximation error within the band.
+    ///
+    /// A band with twice the weight of another will end up with half the
+    /// ripple.
+    pub weight: Real,
+}
+
+/// Parks-McClellan equiripple filter design.
+///
+/// Finds the linear-phase filter with `num_taps` taps that minimizes the
+/// maximum weighted error over `bands`. Frequencies between bands are "don't
+/// care" transition regions.
+///
+/// Filters with an even number of taps always have zero gain at Nyquist, so
+/// bands with a nonzero gain at `sample_rate / 2` require an odd `num_taps`.
+#[derive(Debug, Clone)]
+pub struct ParksMcClellan {
+    pub sample_rate: Real,
+    pub num_taps: usize,
+    pub bands: Vec<Band>,
+}
+
+impl ParksMcClellan {
+    /// Build the band specification equivalent to a `WindowMethod` passband.
+    ///
+    /// Each cutoff frequency is placed in the middle of a transition band of
+    /// the given width, which matches the response of a windowed-sinc design
+    /// with the same parameters. The number of taps is rounded up to be odd.
+    pub fn from_passband(
+        gain: Real,
+        sample_rate: Real,
+        passband: Passband,
+        transition_width: Real,
+        num_taps: usize,
+    ) -> Self {
+        let nyquist = 0.5 * sample_rate;
+        let half_width = 0.5 * transition_width;
+
+        // (start, end, gain) with edges relative to the cutoff frequencies,
+        // before accounting for the transition bands.
+        let edges: &[(Real, Real, Real)] = match passband {
+            Passband::LowPass { cutoff } => &[(0.0, cutoff, gain), (cutoff, nyquist, 0.0)],
+            Passband::HighPass { cutoff } => &[(0.0, cutoff, 0.0), (cutoff, nyquist, gain)],
+            Passband::BandPass {
+                low_cutoff,
+                high_cutoff,
+            } => &[
+                (0.0, low_cutoff, 0.0),
+                (low_cutoff, high_cutoff, gain),
+                (high_cutoff, nyquist, 0.0),
+            ],
+            Passband::BandReject {
+                low_cutoff,
+                high_cutoff,
+            } => &[
+                (0.0, low_cutoff, gain),
+                (low_cutoff, high_cutoff, 0.0),
+                (high_cutoff, nyquist, gain),
+            ],
+        };
+
+        let bands = edges
+            .iter()
+            .map(|&(low, high, gain)| Band {
+                low: if low > 0.0 { low + half_width } else { low },
+                high: if high < nyquist {
+                    high - half_width
+                } else {
+                    high
+                },
+                gain,
+                weight: 1.0,
+            })
+            .filter(|band| band.low < band.high)
+            .collect();
+
+        Self {
+            sample_rate,
+            num_taps: num_taps | 1,
+            bands,
+        }
+    }
+
+    /// Design the filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the design doesn't converge to an equiripple response. This
+    /// happens when the transition bands are too narrow for the number of
+    /// taps, and also when they are so wide that the ripple is below what
+    /// double precision can resolve (typically beyond 120 to 150 dB of
+    /// attenuation); use fewer taps in that case.
+    pub fn taps(&self) -> Box<[Real]> {
+        assert!(self.num_taps > 0);
+        assert!(!self.bands.is_empty());
+
+        let num_taps = self.num_taps;
+        let odd = num_taps % 2 == 1;
+        // Number of cosine coefficients in the zero-phase response.
+        let num_coeffs = num_taps.div_ceil(2);
+
+        let grid = Grid::new(self, num_coeffs, odd);
+
+        // A single gain over all bands is met exactly by an impulse. The
+        // exchange would only chase rounding noise, so handle it up front.
+        let gain = self.bands[0].gain;
+        if odd && self.bands.iter().all(|band| band.gain == gain) {
+            let mut taps = vec![0.0; num_taps];
+            taps[num_taps / 2] = gain;
+            return taps.into();
+        }
+
+        let (approx, delta) = remez(&grid, num_coeffs);
+
+        // Recover the impulse response by sampling the zero-phase response at
+        // `num_taps` equally-spaced frequencies and taking the inverse DFT.
+        // (For an even number of taps, the response at Nyquist is zero.)
+        let n = num_taps as f64;
+        let center = 0.5 * (n - 1.0);
+        let response: Vec<f64> = (0..=(num_taps - 1) / 2)
+            .map(|m| {
+                let freq = m as f64 / n;
+                approx.evaluate(freq) * grid.scale(freq)
+            })
+            .collect();
+
+        let taps: Vec<f64> = (0..num_taps)
+            .map(|i| {
+                let t = i as f64 - center;
+                let sum = response[0]
+                    + 2.0
+                        * response[1..]
+                            .iter()
+                            .enumerate()
+                            .map(|(m, &a)| {
+                                a * (std::f64::consts::TAU * (m + 1) as f64 / n * t).cos()
+                            })
+                            .sum::<f64>();
+                sum / n
+            })
+            .collect();
+
+        // The exchange can stop early, or break down numerically when the spec
+        // can't be met or the ripple is too small to resolve, so check that the
+        // response of the taps is (close to) equiripple over the bands, or
+        // meets the spec exactly.
+        let limit = (1.0 + EQUIRIPPLE_TOLERANCE) * delta.abs() + NEGLIGIBLE_ERROR;
+        let equiripple = (0..grid.len()).all(|i| {
+            let freq = grid.freq[i];
+            let response: f64 = taps
+                .iter()
+                .enumerate()
+                .map(|(k, &tap)| tap * (std::f64::consts::TAU * freq * (k as f64 - center)).cos())
+                .sum();
+            let error = grid.weight[i] * (grid.desired[i] - response / grid.scale(freq));
+            error.abs() <= limit
+        });
+        assert!(
+            equiripple,
+            "equiripple design failed to converge; the transition bands are too narrow for \
+             the number of taps, or the ripple is too small to resolve"
+        );
+
+        taps.into_iter().map(|tap| tap as Real).collect()
+    }
+
+    pub fn build<T: FirSample>(&self) -> Fir<T> {
+        Fir::new(self.taps())
+    }
+}
+
+/// Dense frequency grid over the bands of interest.
+///
+/// Frequencies are normalized to cycles per sample (0.0 to 0.5). For an even
+/// number of taps, the response contains a fixed `cos(PI * f)` factor which is
+/// divided out of the desired response and multiplied into the weight, so that
+/// the remaining response is a plain cosine polynomial.
+struct Grid {
+    freq: Vec<f64>,
+    desired: Vec<f64>,
+    weight: Vec<f64>,
+    /// Index of the band that each grid point belongs to.
+    band: Vec<usize>,
+    odd: bool,
+}
+
+impl Grid {
+    fn new(spec: &ParksMcClellan, num_coeffs: usize, odd: bool) -> Self {
+        let step = 0.5 / (GRID_DENSITY * num_coeffs) as f64;
+        let mut grid = Self {
+            freq: Vec::new(),
+            desired: Vec::new(),
+            weight: Vec::new(),
+            band: Vec::new(),
+            odd,
+        };
+
+        for (band_index, band) in spec.bands.iter().enumerate() {
+            let low = (band.low / spec.sample_rate) as f64;
+            let mut high = (band.high / spec.sample_rate) as f64;
+            assert!(
+                0.0 <= low && low < high && high <= 0.5,
+                "band edges must be increasing and within 0..=sample_rate/2"
+            );
+            if !odd {
+                // The response is fixed at zero at Nyquist.
+                high = high.min(0.5 - step);
+            }
+
+            let num_points = (((high - low) / step).round() as usize).max(1);
+            for i in 0..=num_points {
+                let freq = low + (high - low) * i as f64 / num_points as f64;
+                let scale = grid.scale(freq);
+                grid.freq.push(freq);
+                grid.desired.push(band.gain as f64 / scale);
+                grid.weight.push(band.weight as f64 * scale);
+                grid.band.push(band_index);
+            }
+        }
+        assert!(
+            grid.freq.len() > num_coeffs,
+            "not enough bandwidth in bands to design the filter"
+        );
+        grid
+    }
+
+    /// The fixed factor of the zero-phase response at the given frequency.
+    fn scale(&self, freq: f64) -> f64 {
+        if self.odd {
+            1.0
+        } else {
+            (std::f64::consts::PI * freq).cos()
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.freq.len()
+    }
+}
+
+/// Lagrange interpolation of the current best approximation, in barycentric
+/// form over `x = cos(2 * PI * f)`.
+struct Approximation {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl Approximation {
+    fn new(x: Vec<f64>, y: Vec<f64>) -> Self {
+        let weights = barycentric_weights(&x);
+        Self { x, y, weights }
+    }
+
+    fn evaluate(&self, freq: f64) -> f64 {
+        let x = (std::f64::consts::TAU * freq).cos();
+        let mut numerator = 0.0;
+        let mut denominator = 0.0;
+        for ((&xk, &yk), &wk) in self.x.iter().zip(&self.y).zip(&self.weights) {
+            let diff = x - xk;
+            if diff.abs() < 1e-15 {
+                return yk;
+            }
+            let c = wk / diff;
+            numerator += c * yk;
+            denominator += c;
+        }
+        numerator / denominator
+    }
+}
+
+fn barycentric_weights(x: &[f64]) -> Vec<f64> {
+    // Each difference is scaled by 2 to keep the products from overflowing or
+    // underflowing; the common factor cancels in the barycentric formula.
+    (0..x.len())
+        .map(|k| {
+            let product: f64 = (0..x.len())
+                .filter(|&j| j != k)
+                .map(|j| 2.0 * (x[k] - x[j]))
+                .product();
+            1.0 / product
+        })
+        .collect()
+}
+
+/// Find the best approximation, along with its weighted deviation.
+fn remez(grid: &Grid, num_coeffs: usize) -> (Approximation, f64) {
+    let num_extremals = num_coeffs + 1;
+
+    // Initial guess: extremal frequencies spread evenly over the grid.
+    let mut extremals: Vec<usize> = (0..num_extremals)
+        .map(|i| i * (grid.len() - 1) / (num_extremals - 1))
+        .collect();
+
+    let mut error = vec![0.0; grid.len()];
+    let mut iteration = 0;
+    loop {
+        iteration += 1;
+
+        let x: Vec<f64> = extremals
+            .iter()
+            .map(|&i| (std::f64::consts::TAU * grid.freq[i]).cos())
+            .collect();
+        let weights = barycentric_weights(&x);
+
+        // Deviation achieved when the error alternates at the extremals.
+        let mut numerator = 0.0;
+        let mut denominator = 0.0;
+        for (k, (&i, &w)) in extremals.iter().zip(&weights).enumerate() {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            numerator += w * grid.desired[i];
+            denominator += sign * w / grid.weight[i];
+        }
+        let delta = numerator / denominator;
+
+        // Interpolate through all but the last extremal; the last one is
+        // satisfied automatically by the choice of delta.
+        let y: Vec<f64> = extremals[..num_coeffs]
+            .iter()
+            .enumerate()
+            .map(|(k, &i)| {
+                let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                grid.desired[i] - sign * delta / grid.weight[i]
+            })
+            .collect();
+        let approx = Approximation::new(x[..num_coeffs].to_vec(), y);
+
+        for (i, slot) in error.iter_mut().enumerate() {
+            *slot = grid.weight[i] * (grid.desired[i] - approx.evaluate(grid.freq[i]));
+        }
+
+        // Keep exchanging until the error at the extremals is equiripple. Only
+        // fall back to keeping the previous extremals as candidates, since the
+        // signs of the error there are just noise when delta is tiny.
+        let new_extremals = find_extremals(grid, &error, num_extremals, delta.abs(), &[])
+            .or_else(|| find_extremals(grid, &error, num_extremals, delta.abs(), &extremals))
+            .filter(|new_extremals| {
+                let (min, max) = new_extremals
+                    .iter()
+                    .map(|&i| error[i].abs())
+                    .fold((f64::INFINITY, 0.0f64), |(min, max), e| {
+                        (min.min(e), max.max(e))
+                    });
+                (max - min) > 1e-6 * max
+            });
+        match new_extremals {
+            Some(new_extremals) if iteration < MAX_ITERATIONS => extremals = new_extremals,
+            _ => return (approx, delta),
+        }
+    }
+}
+
+/// Locate `count` alternating extrema of the error function.
+///
+/// The `extra` points are candidates along with the local extrema. Passing
+/// the previous extremals, where the error alternates with magnitude
+/// `min_magnitude`, gives enough alternations even where the error has too
+/// few local extrema, e.g. in a narrow band.
+fn find_extremals(
+    grid: &Grid,
+    error: &[f64],
+    count: usize,
+    min_magnitude: f64,
+    extra: &[usize],
+) -> Option<Vec<usize>> {
+    let len = error.len();
+    // Compare signed values, so that a point next to a sign change still
+    // counts as an extremum. Band edges are always candidates, since the
+    // error is only defined on one side of them.
+    let is_extremum = |i: usize, j: usize| {
+        let sign = error[i].signum();
+        grid.band[i] != grid.band[j] || sign * error[i] >= sign * error[j]
+    };
+    let candidates: Vec<usize> = (0..len)
+        .filter(|&i| {
+            let local = (i == 0 || is_extremum(i, i - 1))
+                && (i + 1 == len || is_extremum(i, i + 1))
+                && error[i].abs() >= min_magnitude * (1.0 - 1e-3);
+            local || extra.binary_search(&i).is_ok()
+        })
+        .collect();
+
+    // Of consecutive extrema with the same sign, keep only the largest.
+    let mut alternating: Vec<usize> = Vec::with_capacity(candidates.len());
+    for i in candidates {
+        match alternating.last_mut() {
+            Some(last) if error[*last].signum() == error[i].signum() => {
+                if error[i].abs() > error[*last].abs() {
+                    *last = i;
+                }
+            }
+            _ => alternating.push(i),
+        }
+    }
+
+    // Drop extra extrema from the ends, smallest first, to preserve alternation.
+    while alternating.len() > count {
+        let first = error[alternating[0]].abs();
+        let last = error[*alternating.last().unwrap()].abs();
+        if first < last {
+            alternating.remove(0);
+        } else {
+            alternating.pop();
+        }
+    }
+
+    if alternating.len() == count {
+        Some(alternating)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_gain_is_an_impulse() {
+        let design = ParksMcClellan {
+            sample_rate: 8000.0,
+            num_taps: 73,
+            bands: vec![Band {
+                low: 0.0,
+                high: 2800.0,
+                gain: 0.5,
+                weight: 1.0,
+            }],
+        };
+        let taps = design.taps();
+        for (i, &tap) in taps.iter().enumerate() {
+            assert_eq!(tap, if i == 36 { 0.5 } else { 0.0 });
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to converge")]
+    fn unmeetable_spec_panics() {
+        // 200 Hz transitions at 8 kHz need far more than 13 taps.
+        let design = ParksMcClellan {
+            sample_rate: 8000.0,
+            num_taps: 13,
+            bands: vec![
+                Band {
+                    low: 0.0,
+                    high: 1100.0,
+                    gain: 0.0,
+                    weight: 1.0,
+                },
+                Band {
+                    low: 1300.0,
+                    high: 1500.0,
+                    gain: 1.0,
+                    weight: 1.0,
+                },
+                Band {
+                    low: 1700.0,
+                    high: 4000.0,
+                    gain: 0.0,
+                    weight: 1.0,
+                },
+            ],
+        };
+        design.taps();
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to converge")]
+    fn unresolvable_ripple_panics() {
+        // Over 300 dB of attenuation.
+        ParksMcClellan::from_passband(
+            1.0,
+            8000.0,
+            Passband::LowPass { cutoff: 1000.0 },
+            1000.0,
+            201,
+        )
+        .taps();
+    }
+}
@@ -0,0 +1,220 @@
+//! Frequency response analysis of FIR filters.
+
+use std::io::{self, Write};
+
+use crate::{
+    iq::IQ,
+    math::{Real, TAU},
+    sample::Sample,
+};
+
+/// Response of a filter at a single frequency.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponsePoint {
+    /// Frequency, in Hz.
+    pub freq: Real,
+    /// Complex gain at this frequency.
+    pub response: IQ,
+    /// Group delay, in samples.
+    pub group_delay: Real,
+}
+
+impl ResponsePoint {
+    pub fn magnitude(&self) -> Real {
+        self.response.magnitude()
+    }
+
+    pub fn magnitude_db(&self) -> Real {
+        20.0 * self.magnitude().log10()
+    }
+
+    /// Phase of the response in radians, between -PI and +PI.
+    pub fn phase(&self) -> Real {
+        self.response.phase()
+    }
+}
+
+/// Frequency response of a filter evaluated over a grid of frequencies.
+#[derive(Debug, Clone)]
+pub struct FrequencyResponse {
+    pub points: Vec<ResponsePoint>,
+}
+
+impl FrequencyResponse {
+    /// Evaluate the response of the given impulse response at each frequency.
+    pub fn evaluate<C>(taps: &[C], sample_rate: Real, freqs: impl IntoIterator<Item = Real>) -> Self
+    where
+        C: Copy + Into<IQ>,
+    {
+        let points = freqs
+            .into_iter()
+            .map(|freq| {
+                let omega = TAU * freq / sample_rate;
+                let mut response = IQ::ZERO;
+                let mut ramped = IQ::ZERO;
+                for (n, &tap) in taps.iter().enumerate() {
+                    let term = tap.into() * IQ::new_polar(-omega * n as Real, 1.0);
+                    response = response + term;
+                    ramped = ramped + term * n as Real;
+                }
+                // Group delay is -d(phase)/d(omega), which works out to the
+                // real part of DTFT(n * h[n]) / DTFT(h[n]).
+                let group_delay = (ramped * response.conj()).i / response.magnitude_squared();
+                ResponsePoint {
+                    freq,
+                    response,
+                    group_delay,
+                }
+            })
+            .collect();
+        Self { points }
+    }
+
+    /// `num_points` equally spaced frequencies from `start` to `end` inclusive.
+    pub fn linear_grid(start: Real, end: Real, num_points: usize) -> impl Iterator<Item = Real> {
+        let step = if num_points > 1 {
+            (end - start) / (num_points - 1) as Real
+        } else {
+            0.0
+        };
+        (0..num_points).map(move |i| start + step * i as Real)
+    }
+
+    /// Phase of the response in radians, unwrapped so that it is continuous
+    /// across the grid.
+    pub fn unwrapped_phase(&self) -> Vec<Real> {
+        let mut offset = 0.0;
+        let mut last: Option<Real> = None;
+        self.points
+            .iter()
+            .map(|point| {
+                let phase = point.phase();
+                if let Some(last) = last {
+                    let diff = phase - last;
+                    offset -= TAU * (diff / TAU).round();
+                }
+                last = Some(phase);
+                phase + offset
+            })
+            .collect()
+    }
+
+    /// The points with frequencies between `low` and `high` inclusive.
+    pub fn band(&self, low: Real, high: Real) -> impl Iterator<Item = &ResponsePoint> {
+        self.points
+            .iter()
+            .filter(move |point| low <= point.freq && point.freq <= high)
+    }
+
+    /// Largest magnitude in dB between `low` and `high`, e.g. to check the
+    /// attenuation in a stopband.
+    pub fn max_db(&self, low: Real, high: Real) -> Real {
+        self.band(low, high)
+            .map(ResponsePoint::magnitude_db)
+            .fold(Real::NEG_INFINITY, Real::max)
+    }
+
+    /// Smallest magnitude in dB between `low` and `high`.
+    pub fn min_db(&self, low: Real, high: Real) -> Real {
+        self.band(low, high)
+            .map(ResponsePoint::magnitude_db)
+            .fold(Real::INFINITY, Real::min)
+    }
+
+    /// Peak-to-peak ripple in dB between `low` and `high`, e.g. within a
+    /// passband.
+    pub fn ripple_db(&self, low: Real, high: Real) -> Real {
+        self.max_db(low, high) - self.min_db(low, high)
+    }
+
+    /// Write the response as CSV, with one row per frequency and a header row.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "frequency,real,imag,magnitude_db,phase,unwrapped_phase,group_delay"
+        )?;
+        for (point, unwrapped) in self.points.iter().zip(self.unwrapped_phase()) {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                point.freq,
+                point.response.i,
+                point.response.q,
+                point.magnitude_db(),
+                point.phase(),
+                unwrapped,
+                point.group_delay,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{remez::ParksMcClellan, Passband, Window, WindowMethod};
+
+    const SAMPLE_RATE: Real = 8000.0;
+    const CUTOFF: Real = 1000.0;
+    const TRANSITION_WIDTH: Real = 400.0;
+
+    /// Passband ripple in dB for a peak amplitude error of `-attenuation` dB.
+    fn ripple_for(attenuation: Real) -> Real {
+        let delta = (10.0 as Real).powf(-attenuation / 20.0);
+        20.0 * ((1.0 + delta) / (1.0 - delta)).log10()
+    }
+
+    fn check(taps: &[Real], attenuation: Real) {
+        let passband_edge = CUTOFF - 0.5 * TRANSITION_WIDTH;
+        let stopband_edge = CUTOFF + 0.5 * TRANSITION_WIDTH;
+        let response = FrequencyResponse::evaluate(
+            taps,
+            SAMPLE_RATE,
+            FrequencyResponse::linear_grid(0.0, 0.5 * SAMPLE_RATE, 2001),
+        );
+
+        let ripple = response.ripple_db(0.0, passband_edge);
+        assert!(
+            ripple <= ripple_for(attenuation),
+            "passband ripple {ripple} dB"
+        );
+        assert!(response.max_db(0.0, passband_edge).abs() < ripple);
+        let stopband = response.max_db(stopband_edge, 0.5 * SAMPLE_RATE);
+        assert!(stopband <= -attenuation, "stopband {stopband} dB");
+
+        // Symmetric taps have a constant group delay of half their length.
+        let center = 0.5 * (taps.len() - 1) as Real;
+        for point in response.band(0.0, passband_edge) {
+            assert!((point.group_delay - center).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn window_method_meets_spec() {
+        let attenuation = 60.0;
+        let design = WindowMethod {
+            gain: 1.0,
+            sample_rate: SAMPLE_RATE,
+            passband: Passband::LowPass { cutoff: CUTOFF },
+            transition_width: Some(TRANSITION_WIDTH),
+            attenuation: Some(attenuation),
+            num_taps: None,
+            window: Window::kaiser(attenuation),
+        };
+        check(&design.taps(), attenuation);
+    }
+
+    #[test]
+    fn parks_mcclellan_meets_spec() {
+        // A few taps fewer than the 75 that the Kaiser design above needs.
+        let design = ParksMcClellan::from_passband(
+            1.0,
+            SAMPLE_RATE,
+            Passband::LowPass { cutoff: CUTOFF },
+            TRANSITION_WIDTH,
+            71,
+        );
+        check(&design.taps(), 60.0);
+    }
+}
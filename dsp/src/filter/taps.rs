@@ -0,0 +1,563 @@
+//! Loading and saving filter taps in formats used by other tools.
+//!
+//! - Plain text and CSV, as written by Octave's `save -ascii`, `csvwrite` or
+//!   NumPy's `savetxt`: numbers separated by whitespace, commas or
+//!   semicolons, with `#` and `%` starting comments.
+//! - NumPy's `.npy` binary format, for one-dimensional arrays.
+//! - The CSV-like taps files saved by GNU Radio's filter design tool.
+//!
+//! Complex taps are written in Python's notation, e.g. `(0.5-0.25j)`.
+//!
+//! # Examples
+//!
+//! Taps survive a round trip through each format:
+//!
+//! ```
+//! use k9api_dsp::filter::{taps, Fir, Passband, Window, WindowMethod};
+//! use k9api_dsp::iq::IQ;
+//! use k9api_dsp::math::Real;
+//!
+//! let design = WindowMethod {
+//!     gain: 1.0,
+//!     sample_rate: 8000.0,
+//!     passband: Passband::LowPass { cutoff: 1000.0 },
+//!     transition_width: Some(500.0),
+//!     attenuation: None,
+//!     num_taps: None,
+//!     window: Window::HAMMING,
+//! };
+//! let fir: Fir = design.build();
+//! let original = fir.taps();
+//!
+//! let mut text = Vec::new();
+//! taps::write_text(&mut text, original).unwrap();
+//! assert_eq!(taps::read_text::<Real>(&text[..]).unwrap(), original);
+//!
+//! let mut csv = Vec::new();
+//! taps::write_csv(&mut csv, original).unwrap();
+//! assert_eq!(taps::read_text::<Real>(&csv[..]).unwrap(), original);
+//!
+//! let mut npy = Vec::new();
+//! taps::write_npy(&mut npy, original).unwrap();
+//! assert_eq!(taps::read_npy::<Real>(&npy[..]).unwrap(), original);
+//!
+//! let mut grc = Vec::new();
+//! taps::write_gnuradio(&mut grc, original).unwrap();
+//! assert_eq!(taps::read_gnuradio::<Real>(&grc[..]).unwrap(), original);
+//!
+//! let shifted = design.build_shifted(1500.0);
+//! let mut text = Vec::new();
+//! taps::write_text(&mut text, shifted.taps()).unwrap();
+//! assert_eq!(taps::read_text::<IQ>(&text[..]).unwrap(), shifted.taps());
+//!
+//! let mut npy = Vec::new();
+//! taps::write_npy(&mut npy, shifted.taps()).unwrap();
+//! assert_eq!(taps::read_npy::<IQ>(&npy[..]).unwrap(), shifted.taps());
+//! ```
+//!
+//! Files from other tools are read the same way:
+//!
+//! ```
+//! use k9api_dsp::filter::{taps, Fir};
+//! use k9api_dsp::math::Real;
+//!
+//! let octave = "% Created by Octave\n 0.25 0.5 0.25\n";
+//! let fir: Fir = Fir::new(taps::read_text::<Real>(octave.as_bytes()).unwrap());
+//! assert_eq!(fir.taps(), [0.25, 0.5, 0.25]);
+//!
+//! let grc = "restype,fir\nfiltertype,lpf\ngain,1.0\ntaps,0.25,0.5,0.25\n";
+//! assert_eq!(taps::read_gnuradio::<Real>(grc.as_bytes()).unwrap(), [0.25, 0.5, 0.25]);
+//! ```
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{iq::IQ, math::Real};
+
+/// Tap types that can be loaded and saved.
+pub trait Tap: Copy + Sized {
+    /// Type code used for this tap type in `.npy` files.
+    const NPY_DESCR: &'static str;
+
+    fn parse(text: &str) -> Option<Self>;
+
+    fn format(&self) -> String;
+
+    /// Construct a tap from its real and imaginary parts, if it can represent
+    /// them.
+    fn from_parts(re: f64, im: f64) -> Option<Self>;
+
+    fn write_npy(&self, writer: &mut impl Write) -> io::Result<()>;
+}
+
+/// `.npy` type code for `Real`, either `<f4` or `<f8`.
+const REAL_NPY_DESCR: &str = if std::mem::size_of::<Real>() == 4 {
+    "<f4"
+} else {
+    "<f8"
+};
+
+impl Tap for Real {
+    const NPY_DESCR: &'static str = REAL_NPY_DESCR;
+
+    fn parse(text: &str) -> Option<Self> {
+        text.parse().ok()
+    }
+
+    fn format(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_parts(re: f64, im: f64) -> Option<Self> {
+        (im == 0.0).then_some(re as Real)
+    }
+
+    fn write_npy(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl Tap for IQ {
+    const NPY_DESCR: &'static str = if std::mem::size_of::<Real>() == 4 {
+        "<c8"
+    } else {
+        "<c16"
+    };
+
+    fn parse(text: &str) -> Option<Self> {
+        let text = text
+            .strip_prefix('(')
+            .and_then(|text| text.strip_suffix(')'))
+            .unwrap_or(text);
+        let Some(text) = text.strip_suffix(['j', 'J']) else {
+            return Some(IQ::new(text.parse().ok()?, 0.0));
+        };
+        // Split before the sign of the imaginary part, skipping a leading
+        // sign and the signs of exponents.
+        let split = text
+            .char_indices()
+            .skip(1)
+            .filter(|&(i, c)| (c == '+' || c == '-') && !text[..i].ends_with(['e', 'E']))
+            .map(|(i, _)| i)
+            .last();
+        match split {
+            Some(i) => Some(IQ::new(text[..i].parse().ok()?, text[i..].parse().ok()?)),
+            None => Some(IQ::new(0.0, text.parse().ok()?)),
+        }
+    }
+
+    fn format(&self) -> String {
+        if self.q.is_sign_negative() {
+            format!("({}{}j)", self.i, self.q)
+        } else {
+            format!("({}+{}j)", self.i, self.q)
+        }
+    }
+
+    fn from_parts(re: f64, im: f64) -> Option<Self> {
+        Some(IQ::new(re as Real, im as Real))
+    }
+
+    fn write_npy(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.i.to_le_bytes())?;
+        writer.write_all(&self.q.to_le_bytes())
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn parse_list<C: Tap>(text: &str, taps: &mut Vec<C>) -> io::Result<()> {
+    for field in text
+        .split([',', ';', ' ', '\t'])
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+    {
+        taps.push(C::parse(field).ok_or_else(|| invalid_data(format!("invalid tap `{field}`")))?);
+    }
+    Ok(())
+}
+
+/// Read taps from plain text or CSV.
+///
+/// The taps may be on one line or several, in any mix of separators.
+pub fn read_text<C: Tap>(reader: impl BufRead) -> io::Result<Vec<C>> {
+    let mut taps = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let content = line.split(['#', '%']).next().unwrap_or("");
+        parse_list(content, &mut taps)?;
+    }
+    Ok(taps)
+}
+
+/// Write taps as plain text, one per line.
+pub fn write_text<C: Tap>(mut writer: impl Write, taps: &[C]) -> io::Result<()> {
+    for tap in taps {
+        writeln!(writer, "{}", tap.format())?;
+    }
+    Ok(())
+}
+
+/// Write taps as a single line of CSV.
+pub fn write_csv<C: Tap>(mut writer: impl Write, taps: &[C]) -> io::Result<()> {
+    let fields: Vec<String> = taps.iter().map(Tap::format).collect();
+    writeln!(writer, "{}", fields.join(","))
+}
+
+/// Read taps from a GNU Radio filter design taps file.
+///
+/// Only the `taps` line is used; the design parameters on the other lines
+/// are ignored.
+pub fn read_gnuradio<C: Tap>(reader: impl BufRead) -> io::Result<Vec<C>> {
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(list) = line.trim().strip_prefix("taps,") {
+            let mut taps = Vec::new();
+            parse_list(list, &mut taps)?;
+            return Ok(taps);
+        }
+    }
+    Err(invalid_data("no `taps` line in GNU Radio taps file"))
+}
+
+/// Write taps as a GNU Radio filter design taps file.
+pub fn write_gnuradio<C: Tap>(mut writer: impl Write, taps: &[C]) -> io::Result<()> {
+    writeln!(writer, "restype,fir")?;
+    write!(writer, "taps,")?;
+    write_csv(writer, taps)
+}
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Read taps from a `.npy` file containing a one-dimensional array.
+///
+/// Arrays of little-endian 32- or 64-bit floats are accepted, and complex
+/// arrays too if `C` is complex.
+pub fn read_npy<C: Tap>(mut reader: impl Read) -> io::Result<Vec<C>> {
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+        return Err(invalid_data("not a .npy file"));
+    }
+    let header_len = match preamble[6] {
+        1 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => return Err(invalid_data(format!("unsupported .npy version {version}"))),
+    };
+    // Neither the header length nor the shape can be trusted to allocate up
+    // front, so both are read incrementally.
+    let mut header = Vec::new();
+    reader
+        .by_ref()
+        .take(header_len as u64)
+        .read_to_end(&mut header)?;
+    if header.len() < header_len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let header = String::from_utf8(header).map_err(|_| invalid_data("invalid .npy header"))?;
+
+    let descr = npy_header_value(&header, "descr")
+        .map(|value| value.trim_matches(['\'', '"']))
+        .ok_or_else(|| invalid_data("missing `descr` in .npy header"))?;
+    if npy_header_value(&header, "fortran_order") == Some("True") {
+        return Err(invalid_data(
+            "Fortran-ordered .npy arrays are not supported",
+        ));
+    }
+    let shape = npy_header_value(&header, "shape")
+        .ok_or_else(|| invalid_data("missing `shape` in .npy header"))?;
+    let dims: Vec<&str> = shape
+        .trim_matches(['(', ')'])
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .collect();
+    let len: usize = match dims[..] {
+        [len] => len
+            .parse()
+            .map_err(|_| invalid_data("invalid .npy shape"))?,
+        _ => {
+            return Err(invalid_data(
+                "only one-dimensional .npy arrays are supported",
+            ))
+        }
+    };
+
+    let mut taps = Vec::with_capacity(len.min(1 << 16));
+    for _ in 0..len {
+        let (re, im) = match descr {
+            "<f4" => (read_f32(&mut reader)?, 0.0),
+            "<f8" => (read_f64(&mut reader)?, 0.0),
+            "<c8" => (read_f32(&mut reader)?, read_f32(&mut reader)?),
+            "<c16" => (read_f64(&mut reader)?, read_f64(&mut reader)?),
+            _ => return Err(invalid_data(format!("unsupported .npy type `{descr}`"))),
+        };
+        taps.push(
+            C::from_parts(re, im)
+                .ok_or_else(|| invalid_data("complex .npy array cannot be read as real taps"))?,
+        );
+    }
+    Ok(taps)
+}
+
+/// Write taps as a `.npy` file containing a one-dimensional array.
+pub fn write_npy<C: Tap>(mut writer: impl Write, taps: &[C]) -> io::Result<()> {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({},), }}",
+        C::NPY_DESCR,
+        taps.len()
+    );
+    // The data must start at a multiple of 64 bytes, after a newline.
+    let unpadded = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.extend(std::iter::repeat_n(
+        ' ',
+        unpadded.next_multiple_of(64) - unpadded,
+    ));
+    header.push('\n');
+
+    writer.write_all(NPY_MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for tap in taps {
+        tap.write_npy(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// Find the value of a key in the Python dict literal of a `.npy` header.
+fn npy_header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}'"))? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find([',', '}'])?
+    };
+    Some(rest[..end].trim())
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes) as f64)
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAPS: [Real; 5] = [0.125, -0.5, 1.0, 3.0e-5, -2.5e-7];
+
+    fn complex_taps() -> Vec<IQ> {
+        TAPS.iter()
+            .zip(TAPS.iter().rev())
+            .map(|(&i, &q)| IQ::new(i, q))
+            .collect()
+    }
+
+    /// A `.npy` file with the given header fields and data.
+    fn npy(version: u8, descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let header =
+            format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}\n");
+        let mut file = NPY_MAGIC.to_vec();
+        file.extend([version, 0]);
+        if version == 1 {
+            file.extend((header.len() as u16).to_le_bytes());
+        } else {
+            file.extend((header.len() as u32).to_le_bytes());
+        }
+        file.extend(header.as_bytes());
+        file.extend(data);
+        file
+    }
+
+    /// Overwrite the first occurrence of `from` with `to`, of the same length.
+    fn replace(file: &mut [u8], from: &[u8], to: &[u8]) {
+        let start = file.windows(from.len()).position(|w| w == from).unwrap();
+        file[start..start + to.len()].copy_from_slice(to);
+    }
+
+    fn assert_invalid<T: std::fmt::Debug>(result: io::Result<T>, message: &str) {
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(message), "{error}");
+    }
+
+    fn assert_truncated<T: std::fmt::Debug>(result: io::Result<T>) {
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut buffer = Vec::new();
+        write_text(&mut buffer, &TAPS).unwrap();
+        assert_eq!(read_text::<Real>(&buffer[..]).unwrap(), TAPS);
+
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &TAPS).unwrap();
+        assert_eq!(read_text::<Real>(&buffer[..]).unwrap(), TAPS);
+
+        let mut buffer = Vec::new();
+        write_gnuradio(&mut buffer, &TAPS).unwrap();
+        assert_eq!(read_gnuradio::<Real>(&buffer[..]).unwrap(), TAPS);
+
+        let mut buffer = Vec::new();
+        write_npy(&mut buffer, &TAPS).unwrap();
+        // The data starts at a multiple of 64 bytes.
+        assert_eq!(buffer.len() % 64, (TAPS.len() * size_of::<Real>()) % 64);
+        assert_eq!(read_npy::<Real>(&buffer[..]).unwrap(), TAPS);
+
+        let taps = complex_taps();
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &taps).unwrap();
+        assert_eq!(read_text::<IQ>(&buffer[..]).unwrap(), taps);
+
+        let mut buffer = Vec::new();
+        write_npy(&mut buffer, &taps).unwrap();
+        assert_eq!(read_npy::<IQ>(&buffer[..]).unwrap(), taps);
+    }
+
+    #[test]
+    fn text_formats() {
+        let text = "# comment\n0.5, 0.25;1e-3\t-2 % trailing comment\n\n4";
+        assert_eq!(
+            read_text::<Real>(text.as_bytes()).unwrap(),
+            [0.5, 0.25, 1e-3, -2.0, 4.0]
+        );
+
+        let text = "(1-2j) 3j -4 (-1e-3+2.5e+2j) 5E-1-6E+1J";
+        assert_eq!(
+            read_text::<IQ>(text.as_bytes()).unwrap(),
+            [
+                IQ::new(1.0, -2.0),
+                IQ::new(0.0, 3.0),
+                IQ::new(-4.0, 0.0),
+                IQ::new(-1e-3, 250.0),
+                IQ::new(0.5, -60.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_text() {
+        assert_invalid(read_text::<Real>("0.5, abc, 1".as_bytes()), "`abc`");
+        assert_invalid(read_text::<Real>("(1+2j)".as_bytes()), "`(1+2j)`");
+        assert_invalid(read_text::<IQ>("1+j".as_bytes()), "`1+j`");
+        assert_invalid(read_text::<IQ>("(1+2j".as_bytes()), "`(1+2j`");
+    }
+
+    #[test]
+    fn malformed_gnuradio() {
+        assert_invalid(
+            read_gnuradio::<Real>("restype,fir\ngain,1.0\n".as_bytes()),
+            "no `taps` line",
+        );
+        assert_invalid(read_gnuradio::<Real>("taps,0.5,x\n".as_bytes()), "`x`");
+    }
+
+    #[test]
+    fn npy_f32_and_f64() {
+        let taps = TAPS.map(|tap| tap as f64);
+        let f32_data: Vec<u8> = taps
+            .iter()
+            .flat_map(|&tap| (tap as f32).to_le_bytes())
+            .collect();
+        let f64_data: Vec<u8> = taps.iter().flat_map(|tap| tap.to_le_bytes()).collect();
+        for (descr, data) in [("<f4", &f32_data), ("<f8", &f64_data)] {
+            for version in [1, 2] {
+                let file = npy(version, descr, "(5,)", data);
+                let taps = read_npy::<Real>(&file[..]).unwrap();
+                for (&tap, &expected) in taps.iter().zip(&TAPS) {
+                    assert!((tap - expected).abs() <= 1e-7 * expected.abs());
+                }
+            }
+        }
+
+        let data: Vec<u8> = [1.0f32, -2.0, 3.0, 0.5]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let file = npy(1, "<c8", "(2,)", &data);
+        assert_eq!(
+            read_npy::<IQ>(&file[..]).unwrap(),
+            [IQ::new(1.0, -2.0), IQ::new(3.0, 0.5)]
+        );
+    }
+
+    #[test]
+    fn malformed_npy() {
+        let data = [0; 16];
+        assert_invalid(
+            read_npy::<Real>(&b"PK\x03\x04 not npy"[..]),
+            "not a .npy file",
+        );
+        assert_invalid(
+            read_npy::<Real>(&npy(4, "<f4", "(4,)", &data)[..]),
+            "version 4",
+        );
+        assert_invalid(read_npy::<Real>(&npy(1, "<i4", "(4,)", &data)[..]), "`<i4`");
+        assert_invalid(read_npy::<Real>(&npy(1, ">f8", "(2,)", &data)[..]), "`>f8`");
+        assert_invalid(
+            read_npy::<Real>(&npy(1, "<f4", "(2, 2)", &data)[..]),
+            "one-dimensional",
+        );
+        assert_invalid(read_npy::<Real>(&npy(1, "<f4", "(x,)", &data)[..]), "shape");
+
+        let complex: Vec<u8> = [0.5f32, 1.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+        assert_invalid(
+            read_npy::<Real>(&npy(1, "<c8", "(1,)", &complex)[..]),
+            "cannot be read as real taps",
+        );
+
+        let mut fortran = npy(1, "<f4", "(4,)", &data);
+        replace(&mut fortran, b"False", b"True ");
+        assert_invalid(read_npy::<Real>(&fortran[..]), "Fortran");
+
+        let mut no_descr = npy(1, "<f4", "(4,)", &data);
+        replace(&mut no_descr, b"'descr'", b"'DESCR'");
+        assert_invalid(read_npy::<Real>(&no_descr[..]), "missing `descr`");
+    }
+
+    #[test]
+    fn truncated_npy() {
+        let mut file = Vec::new();
+        write_npy(&mut file, &TAPS).unwrap();
+        // In the preamble, the header, and the data.
+        for len in [4, 9, 20, file.len() - 1] {
+            assert_truncated(read_npy::<Real>(&file[..len]));
+        }
+        let data = [0; 7];
+        assert_truncated(read_npy::<Real>(&npy(1, "<f4", "(2,)", &data)[..]));
+        assert_truncated(read_npy::<Real>(&npy(1, "<f8", "(1,)", &data)[..]));
+    }
+
+    #[test]
+    fn huge_npy_sizes() {
+        let data = [0; 8];
+        let shape = format!("({},)", usize::MAX);
+        assert_truncated(read_npy::<Real>(&npy(1, "<f4", &shape, &data)[..]));
+        assert_truncated(read_npy::<IQ>(&npy(1, "<c16", &shape, &data)[..]));
+
+        let mut file = NPY_MAGIC.to_vec();
+        file.extend([2, 0]);
+        file.extend(u32::MAX.to_le_bytes());
+        file.extend(b"{'descr': '<f4', ");
+        assert_truncated(read_npy::<Real>(&file[..]));
+    }
+}
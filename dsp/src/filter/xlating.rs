@@ -0,0 +1,149 @@
+//! Combined frequency translation, filtering and decimation.
+
+use crate::{
+    filter::ComplexFir,
+    iq::IQ,
+    math::{Real, TAU},
+    sample::Sample,
+};
+
+/// Frequency-translating FIR decimator.
+///
+/// Equivalent to mixing the input down by `center_freq`, filtering it with a
+/// lowpass prototype and keeping every `decimation`th sample, which is the
+/// front end of most receivers. Instead of mixing every input sample, the
+/// prototype's taps are shifted up to `center_freq` (see
+/// [`ComplexFir::frequency_shifted`]), and only the decimated outputs are
+/// computed and then mixed down, at the output rate.
+///
+/// The output matches mixing first up to a constant phase offset, since the
+/// shifted taps are referenced to the center tap. The input may be real or
+/// complex; the output is always complex.
+pub struct FreqXlatingFir {
+    prototype: Box<[Real]>,
+    filter: ComplexFir,
+    decimation: usize,
+    sample_rate: Real,
+    center_freq: Real,
+    /// Phase of the output mixer, in cycles.
+    phase: f64,
+    input: Vec<IQ>,
+}
+
+impl FreqXlatingFir {
+    /// Construct a filter from the taps of a lowpass prototype designed at
+    /// the input sample rate.
+    pub fn new(
+        prototype: impl Into<Box<[Real]>>,
+        decimation: usize,
+        center_freq: Real,
+        sample_rate: Real,
+    ) -> Self {
+        assert!(decimation > 0);
+        let prototype = prototype.into();
+        Self {
+            filter: ComplexFir::frequency_shifted(&prototype, sample_rate, center_freq),
+            prototype,
+            decimation,
+            sample_rate,
+            center_freq,
+            phase: 0.0,
+            input: vec![IQ::ZERO; decimation],
+        }
+    }
+
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    pub fn center_freq(&self) -> Real {
+        self.center_freq
+    }
+
+    /// Retune to a new center frequency.
+    ///
+    /// The samples in the filter haven't been mixed yet, so they are kept, and
+    /// the output is as if the input had been mixed at the new frequency all
+    /// along, without a filter transient. The output mixer carries on from
+    /// its current phase. Since the shifted taps are referenced to the center
+    /// tap, their phase at a given input frequency doesn't depend on
+    /// `center_freq`, so the output phase stays continuous.
+    pub fn set_center_freq(&mut self, center_freq: Real) {
+        let shifted = ComplexFir::frequency_shifted(&self.prototype, self.sample_rate, center_freq);
+        self.filter.set_taps(shifted.taps());
+        self.center_freq = center_freq;
+    }
+
+    /// Filter `input`, producing one output for every `decimation` input
+    /// samples.
+    pub fn process<T: Sample + Into<IQ>>(&mut self, input: &[T], output: &mut [IQ]) {
+        assert_eq!(input.len(), output.len() * self.decimation);
+        let phase_step = self.center_freq as f64 * self.decimation as f64 / self.sample_rate as f64;
+        for (chunk, out) in input.chunks(self.decimation).zip(output) {
+            for (slot, &sample) in self.input.iter_mut().zip(chunk) {
+                *slot = sample.into();
+            }
+            let filtered = self.filter.decimate(&self.input);
+            *out = filtered * IQ::new_polar(-TAU * self.phase as Real, 1.0);
+            self.phase = (self.phase + phase_step).rem_euclid(1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{Passband, Window, WindowMethod};
+
+    #[test]
+    fn retuning_keeps_output_phase_continuous() {
+        let sample_rate = 48000.0;
+        let decimation = 8;
+        let prototype = WindowMethod {
+            gain: 1.0,
+            sample_rate,
+            passband: Passband::LowPass { cutoff: 2000.0 },
+            transition_width: None,
+            attenuation: None,
+            num_taps: Some(129),
+            window: Window::HAMMING,
+        }
+        .taps();
+        let tone: Vec<IQ> = (0..4096)
+            .map(|n| IQ::new_polar(TAU * 6200.0 / sample_rate * n as Real, 1.0))
+            .collect();
+        let step = |freq: Real| TAU * freq * decimation as Real / sample_rate;
+
+        // The tone at 6.2 kHz comes out at 200 Hz, then at 200 Hz plus the
+        // change in center frequency after retuning.
+        for new_center in [5800.0, 5520.0, 6100.0] {
+            let mut xlating =
+                FreqXlatingFir::new(prototype.clone(), decimation, 6000.0, sample_rate);
+            let (before, after) = tone.split_at(2048);
+            let retune = before.len() / decimation;
+            let mut output = vec![IQ::ZERO; tone.len() / decimation];
+            let (out_before, out_after) = output.split_at_mut(retune);
+            xlating.process(before, out_before);
+            xlating.set_center_freq(new_center);
+            xlating.process(after, out_after);
+
+            // Skip the start-up transient of the filter.
+            for m in 32..output.len() {
+                assert!((output[m].magnitude() - 1.0).abs() < 1e-2);
+                // The output for block `m` is mixed down by the mixer's phase
+                // after block `m - 1`, so the new frequency first shows up in
+                // the phase advance after the retuned block.
+                let expected = if m <= retune {
+                    step(200.0)
+                } else {
+                    step(6200.0 - new_center)
+                };
+                let advance = (output[m] * output[m - 1].conj()).phase();
+                assert!(
+                    (advance - expected).abs() < 1e-3,
+                    "{m}: {advance} != {expected}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Saturating fixed-point sample types.
+//!
+//! [`Q15`] and [`Q31`] are signed fractions in `[-1, 1)`, stored in an `i16`
+//! or `i32` with 15 or 31 fraction bits, and [`IQ15`] and [`IQ31`] are their
+//! complex counterparts. Raw 16-bit audio samples are already Q15, so they
+//! can be wrapped and processed without converting.
+//!
+//! Arithmetic saturates at the ends of the range instead of wrapping around,
+//! and products of fixed-point values are rounded to nearest. Multiplying by
+//! a [`Real`], as [`Sample`] requires for gains (e.g. in [`amplify`] and
+//! [`Agc`]), goes through floating point.
+//!
+//! [`Fir`] filters accept fixed-point samples with either real taps, which
+//! round every product, or fixed-point taps from [`quantize`] or
+//! [`WindowMethod::quantized_taps`], which accumulate the products in a wide
+//! integer and round once at the end, like the multiply-accumulate unit of a
+//! DSP. [`CicDecimator`] and [`CicInterpolator`] take fixed-point samples
+//! too, and shift the raw integers straight into their integer registers.
+//!
+//! ```
+//! use k9api_dsp::filter::{Passband, Window, WindowMethod};
+//! use k9api_dsp::fixed::Q15;
+//!
+//! let design = WindowMethod {
+//!     gain: 1.0,
+//!     sample_rate: 8000.0,
+//!     passband: Passband::LowPass { cutoff: 1000.0 },
+//!     transition_width: Some(500.0),
+//!     attenuation: None,
+//!     num_taps: None,
+//!     window: Window::HAMMING,
+//! };
+//! let mut filter = design.build_quantized::<Q15, Q15>();
+//!
+//! let audio: Vec<i16> = vec![10000; 200];
+//! let filtered: Vec<i16> = audio
+//!     .iter()
+//!     .map(|&sample| filter.process_sample(Q15(sample)).0)
+//!     .collect();
+//! assert!((filtered[199] - 10000).abs() < 20);
+//! ```
+//!
+//! [`amplify`]: crate::amplify
+//! [`Agc`]: crate::agc::Agc
+//! [`Fir`]: crate::filter::Fir
+//! [`CicDecimator`]: crate::resample::cic::CicDecimator
+//! [`CicInterpolator`]: crate::resample::cic::CicInterpolator
+//! [`WindowMethod::quantized_taps`]: crate::filter::WindowMethod::quantized_taps
+
+use std::{iter::Sum, ops};
+
+use crate::{filter::kernel::FirSample, iq::IQ, math::Real, sample::Sample};
+
+/// Real fixed-point types.
+pub trait FixedPoint: Sample {
+    const FRACTION_BITS: u32;
+
+    /// Convert from a real value, rounding to nearest and saturating values
+    /// outside `[-1, 1)`.
+    fn from_real(value: Real) -> Self;
+
+    fn to_real(self) -> Real;
+}
+
+/// Quantize real values, such as filter taps, to a fixed-point type.
+///
+/// Values outside `[-1, 1)` saturate, so scale the gain of a design down if
+/// any of its taps are that large.
+pub fn quantize<C: FixedPoint>(values: &[Real]) -> Box<[C]> {
+    values.iter().map(|&value| C::from_real(value)).collect()
+}
+
+macro_rules! fixed_real {
+    ($(#[$attr:meta])* $name:ident, $int:ty, $acc:ty, $bits:expr) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $name(pub $int);
+
+        impl $name {
+            pub const MIN: Self = Self(<$int>::MIN);
+            pub const MAX: Self = Self(<$int>::MAX);
+
+            fn saturate(value: $acc) -> Self {
+                Self(value.clamp(<$int>::MIN as $acc, <$int>::MAX as $acc) as $int)
+            }
+
+            /// Round a sum of products, with twice the fraction bits, back to
+            /// this type.
+            fn round_product(value: $acc) -> Self {
+                Self::saturate((value + (1 << ($bits - 1))) >> $bits)
+            }
+        }
+
+        impl FixedPoint for $name {
+            const FRACTION_BITS: u32 = $bits;
+
+            fn from_real(value: Real) -> Self {
+                // Float to integer casts saturate, and map NaN to zero.
+                Self((value as f64 * (1u64 << $bits) as f64).round() as $int)
+            }
+
+            fn to_real(self) -> Real {
+                (self.0 as f64 / (1u64 << $bits) as f64) as Real
+            }
+        }
+
+        impl From<$int> for $name {
+            fn from(value: $int) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for $int {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl From<$name> for IQ {
+            fn from(value: $name) -> Self {
+                IQ::new(value.to_real(), 0.0)
+            }
+        }
+
+        impl ops::Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self(self.0.saturating_add(rhs.0))
+            }
+        }
+
+        impl ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        impl ops::Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                Self(self.0.saturating_neg())
+            }
+        }
+
+        impl ops::Mul for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                Self::round_product(self.0 as $acc * rhs.0 as $acc)
+            }
+        }
+
+        impl ops::Mul<Real> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Real) -> Self::Output {
+                Self::from_real(self.to_real() * rhs)
+            }
+        }
+
+        impl ops::MulAssign<Real> for $name {
+            fn mul_assign(&mut self, rhs: Real) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl ops::Div<Real> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: Real) -> Self::Output {
+                Self::from_real(self.to_real() / rhs)
+            }
+        }
+
+        /// Sums in a wide integer and saturates once at the end, so that
+        /// intermediate overflows don't change the result.
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                Self::saturate(iter.map(|x| x.0 as $acc).sum())
+            }
+        }
+
+        impl Sample for $name {
+            const ZERO: Self = Self(0);
+
+            fn magnitude(&self) -> Real {
+                self.to_real().abs()
+            }
+
+            fn magnitude_squared(&self) -> Real {
+                self.to_real().powi(2)
+            }
+        }
+
+        impl FirSample<$name> for $name {
+            fn dot(taps: &[$name], samples: &[$name]) -> $name {
+                let sum = samples
+                    .iter()
+                    .zip(taps)
+                    .map(|(sample, tap)| sample.0 as $acc * tap.0 as $acc)
+                    .sum();
+                Self::round_product(sum)
+            }
+        }
+    };
+}
+
+macro_rules! fixed_complex {
+    ($(#[$attr:meta])* $name:ident, $real:ident, $acc:ty) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct $name {
+            pub i: $real,
+            pub q: $real,
+        }
+
+        impl $name {
+            pub fn new(i: $real, q: $real) -> Self {
+                Self { i, q }
+            }
+
+            /// Convert from a floating-point sample, rounding and saturating
+            /// each component.
+            pub fn from_iq(value: IQ) -> Self {
+                Self::new($real::from_real(value.i), $real::from_real(value.q))
+            }
+
+            pub fn to_iq(self) -> IQ {
+                IQ::new(self.i.to_real(), self.q.to_real())
+            }
+
+            pub fn conj(&self) -> Self {
+                Self::new(self.i, -self.q)
+            }
+        }
+
+        impl From<$name> for IQ {
+            fn from(value: $name) -> Self {
+                value.to_iq()
+            }
+        }
+
+        impl ops::Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self::new(self.i + rhs.i, self.q + rhs.q)
+            }
+        }
+
+        impl ops::Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self::new(self.i - rhs.i, self.q - rhs.q)
+            }
+        }
+
+        impl ops::Mul<$real> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: $real) -> Self::Output {
+                Self::new(self.i * rhs, self.q * rhs)
+            }
+        }
+
+        impl ops::Mul<Real> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Real) -> Self::Output {
+                Self::new(self.i * rhs, self.q * rhs)
+            }
+        }
+
+        impl ops::MulAssign<Real> for $name {
+            fn mul_assign(&mut self, rhs: Real) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl ops::Div<Real> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: Real) -> Self::Output {
+                Self::new(self.i / rhs, self.q / rhs)
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                let (i, q) = iter.fold((0, 0), |(i, q): ($acc, $acc), x| {
+                    (i + x.i.0 as $acc, q + x.q.0 as $acc)
+                });
+                Self::new($real::saturate(i), $real::saturate(q))
+            }
+        }
+
+        impl Sample for $name {
+            const ZERO: Self = Self {
+                i: $real(0),
+                q: $real(0),
+            };
+
+            fn magnitude(&self) -> Real {
+                self.magnitude_squared().sqrt()
+            }
+
+            fn magnitude_squared(&self) -> Real {
+                self.i.magnitude_squared() + self.q.magnitude_squared()
+            }
+        }
+
+        impl FirSample<$real> for $name {
+            fn dot(taps: &[$real], samples: &[$name]) -> $name {
+                let (i, q) = samples.iter().zip(taps).fold(
+                    (0, 0),
+                    |(i, q): ($acc, $acc), (sample, tap)| {
+                        let tap = tap.0 as $acc;
+                        (i + sample.i.0 as $acc * tap, q + sample.q.0 as $acc * tap)
+                    },
+                );
+                Self::new($real::round_product(i), $real::round_product(q))
+            }
+        }
+    };
+}
+
+fixed_real!(
+    /// Signed 16-bit fraction with 15 fraction bits, in `[-1, 1)`.
+    Q15,
+    i16,
+    i64,
+    15
+);
+
+fixed_real!(
+    /// Signed 32-bit fraction with 31 fraction bits, in `[-1, 1)`.
+    Q31,
+    i32,
+    i128,
+    31
+);
+
+fixed_complex!(
+    /// Complex sample with [`Q15`] components.
+    IQ15,
+    Q15,
+    i64
+);
+
+fixed_complex!(
+    /// Complex sample with [`Q31`] components.
+    IQ31,
+    Q31,
+    i128
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Least significant bit of each type, as a real value.
+    const LSB15: f64 = 1.0 / 32768.0;
+    const LSB31: f64 = 1.0 / 2147483648.0;
+
+    #[test]
+    fn saturates_at_full_scale() {
+        assert_eq!(Q15::MAX + Q15(1), Q15::MAX);
+        assert_eq!(Q15::MIN - Q15(1), Q15::MIN);
+        assert_eq!(-Q15::MIN, Q15::MAX);
+        assert_eq!(Q31::MAX + Q31(1), Q31::MAX);
+        assert_eq!(Q31::MIN - Q31(1), Q31::MIN);
+        assert_eq!(-Q31::MIN, Q31::MAX);
+
+        for (value, q15, q31) in [
+            (1.0, Q15::MAX, Q31::MAX),
+            (5.0, Q15::MAX, Q31::MAX),
+            (-1.0, Q15::MIN, Q31::MIN),
+            (-5.0, Q15::MIN, Q31::MIN),
+            (Real::NAN, Q15(0), Q31(0)),
+        ] {
+            assert_eq!(Q15::from_real(value), q15, "{value}");
+            assert_eq!(Q31::from_real(value), q31, "{value}");
+        }
+        assert_eq!(Q15::MAX * 2.0, Q15::MAX);
+        assert_eq!(Q31::MIN / 0.5, Q31::MIN);
+        assert_eq!(
+            IQ15::new(Q15::MAX, Q15::MIN) * 4.0,
+            IQ15::new(Q15::MAX, Q15::MIN)
+        );
+    }
+
+    #[test]
+    fn from_real_rounds_to_nearest() {
+        for (lsbs, expected) in [(0.4, 0), (0.6, 1), (1.5, 2), (-0.6, -1), (-1.5, -2)] {
+            assert_eq!(Q15::from_real((lsbs * LSB15) as Real), Q15(expected));
+            assert_eq!(Q31::from_real((lsbs * LSB31) as Real), Q31(expected.into()));
+        }
+
+        // Every Q15 value is exactly representable as a real.
+        for raw in i16::MIN..=i16::MAX {
+            let value = Q15(raw).to_real();
+            assert_eq!(value as f64, raw as f64 * LSB15);
+            assert_eq!(Q15::from_real(value), Q15(raw));
+        }
+        for raw in [i32::MIN, -123456789, -1, 0, 1, 987654321, i32::MAX] {
+            let value = Q31(raw).to_real() as f64;
+            let exact = raw as f64 * LSB31;
+            assert!((value - exact).abs() <= exact.abs() * Real::EPSILON as f64);
+        }
+    }
+
+    #[test]
+    fn product_rounds_and_saturates() {
+        // -1 * -1 = 1 is just out of range.
+        assert_eq!(Q15::MIN * Q15::MIN, Q15::MAX);
+        assert_eq!(Q31::MIN * Q31::MIN, Q31::MAX);
+        assert_eq!(Q15::MAX * Q15::MAX, Q15(32766));
+        assert_eq!(Q31::MAX * Q31::MAX, Q31(i32::MAX - 1));
+
+        // Products round to nearest, with ties going up.
+        let half = Q15(1 << 14);
+        assert_eq!(Q15(3) * half, Q15(2));
+        assert_eq!(Q15(-3) * half, Q15(-1));
+        assert_eq!(Q15(1) * Q15(1), Q15(0));
+        let half = Q31(1 << 30);
+        assert_eq!(Q31(3) * half, Q31(2));
+        assert_eq!(Q31(-3) * half, Q31(-1));
+    }
+
+    #[test]
+    fn sums_saturate_only_at_the_end() {
+        let values = [Q15::MAX, Q15(1000), Q15(-2000)];
+        // Adding one at a time saturates along the way, where the raw
+        // integers would wrap around to a negative value.
+        assert_eq!(Q15::MAX + Q15(1000), Q15::MAX);
+        assert!(i16::MAX.wrapping_add(1000) < 0);
+        let stepwise = values.iter().fold(Q15::ZERO, |sum, &x| sum + x);
+        assert_eq!(stepwise, Q15(i16::MAX - 2000));
+        // `Sum` accumulates in a wide integer, so intermediate overflow
+        // doesn't change the result, which matches the wrapping sum when it
+        // ends up in range.
+        let wrapping = values.iter().fold(0i16, |sum, x| sum.wrapping_add(x.0));
+        assert_eq!(values.iter().copied().sum::<Q15>(), Q15(wrapping));
+        assert_eq!(wrapping, i16::MAX - 1000);
+        assert_eq!([Q31::MAX, Q31::MAX].into_iter().sum::<Q31>(), Q31::MAX);
+
+        let samples = [IQ15::new(Q15::MAX, Q15::MIN); 4];
+        let sum = samples.iter().copied().sum::<IQ15>();
+        assert_eq!(sum, IQ15::new(Q15::MAX, Q15::MIN));
+
+        // Filters accumulate products the same way, rounding once.
+        let taps = [Q15::MAX, Q15::MAX, Q15::MIN];
+        let samples = [Q15::MAX, Q15::MAX, Q15::MAX];
+        let exact = 2 * 32767i64 * 32767 - 32768 * 32767;
+        let expected = Q15(((exact + (1 << 14)) >> 15) as i16);
+        assert_eq!(Q15::dot(&taps, &samples), expected);
+        let samples = samples.map(|x| IQ15::new(x, -x));
+        assert_eq!(IQ15::dot(&taps, &samples), IQ15::new(expected, -expected));
+    }
+}
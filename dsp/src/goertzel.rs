@@ -0,0 +1,288 @@
+//! Streaming single-frequency power detection with the Goertzel algorithm.
+
+use crate::math::{cos, Real, TAU};
+
+/// Power of one tone over a block of samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonePower {
+    /// Mean power of the tone, so a sinusoid with amplitude `A` at exactly the
+    /// detector frequency has a power of `A^2 / 2`.
+    pub absolute: Real,
+    /// Fraction of the total power in the block that is in this tone, between
+    /// 0 and 1.
+    pub relative: Real,
+}
+
+impl TonePower {
+    pub fn absolute_db(&self) -> Real {
+        10.0 * self.absolute.log10()
+    }
+
+    pub fn relative_db(&self) -> Real {
+        10.0 * self.relative.log10()
+    }
+}
+
+/// Second-order resonator at the heart of the Goertzel algorithm.
+#[derive(Debug, Clone)]
+struct Resonator {
+    freq: Real,
+    coeff: Real,
+    s1: Real,
+    s2: Real,
+}
+
+impl Resonator {
+    fn new(freq: Real, sample_rate: Real) -> Self {
+        Self {
+            freq,
+            coeff: 2.0 * cos(TAU * freq / sample_rate),
+            s1: 0.0,
+            s2: 0.0,
+        }
+    }
+
+    fn process_sample(&mut self, sample: Real) {
+        let s0 = sample + self.coeff * self.s1 - self.s2;
+        self.s2 = self.s1;
+        self.s1 = s0;
+    }
+
+    /// Squared magnitude of the DTFT of the block at the resonator frequency,
+    /// after which the state is reset for the next block.
+    fn finish(&mut self) -> Real {
+        let power = self.s1 * self.s1 + self.s2 * self.s2 - self.coeff * self.s1 * self.s2;
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+        power
+    }
+}
+
+/// Accumulates total power and counts samples within a block.
+#[derive(Debug, Clone)]
+struct Block {
+    size: usize,
+    count: usize,
+    energy: Real,
+}
+
+impl Block {
+    fn new(size: usize) -> Self {
+        assert!(size > 0);
+        Self {
+            size,
+            count: 0,
+            energy: 0.0,
+        }
+    }
+
+    /// Add a sample, returning the total energy of the block if it is now
+    /// complete.
+    fn process_sample(&mut self, sample: Real) -> Option<Real> {
+        self.energy += sample * sample;
+        self.count += 1;
+        if self.count == self.size {
+            let energy = self.energy;
+            self.count = 0;
+            self.energy = 0.0;
+            Some(energy)
+        } else {
+            None
+        }
+    }
+
+    fn tone_power(&self, magnitude_squared: Real, energy: Real) -> TonePower {
+        let n = self.size as Real;
+        let absolute = 2.0 * magnitude_squared / (n * n);
+        let mean = energy / n;
+        TonePower {
+            absolute,
+            relative: if mean > 0.0 {
+                (absolute / mean).min(1.0)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Detects the power of a single tone, reporting it every `block_size`
+/// samples.
+///
+/// The frequency doesn't have to fall on a DFT bin of the block. Longer blocks
+/// give a narrower detection bandwidth (about `sample_rate / block_size`) at
+/// the cost of a slower response.
+#[derive(Debug, Clone)]
+pub struct Goertzel {
+    resonator: Resonator,
+    block: Block,
+}
+
+impl Goertzel {
+    pub fn new(freq: Real, sample_rate: Real, block_size: usize) -> Self {
+        Self {
+            resonator: Resonator::new(freq, sample_rate),
+            block: Block::new(block_size),
+        }
+    }
+
+    pub fn freq(&self) -> Real {
+        self.resonator.freq
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block.size
+    }
+
+    /// Process a sample, returning the tone power at the end of each block.
+    pub fn process_sample(&mut self, sample: Real) -> Option<TonePower> {
+        self.resonator.process_sample(sample);
+        let energy = self.block.process_sample(sample)?;
+        Some(self.block.tone_power(self.resonator.finish(), energy))
+    }
+}
+
+/// A set of [`Goertzel`] detectors sharing the same block timing, e.g. for the
+/// tones of DTMF or FSK.
+#[derive(Debug, Clone)]
+pub struct GoertzelBank {
+    resonators: Box<[Resonator]>,
+    block: Block,
+    powers: Box<[TonePower]>,
+    completed: bool,
+}
+
+impl GoertzelBank {
+    pub fn new(freqs: &[Real], sample_rate: Real, block_size: usize) -> Self {
+        Self {
+            resonators: freqs
+                .iter()
+                .map(|&freq| Resonator::new(freq, sample_rate))
+                .collect(),
+            block: Block::new(block_size),
+            powers: vec![
+                TonePower {
+                    absolute: 0.0,
+                    relative: 0.0
+                };
+                freqs.len()
+            ]
+            .into_boxed_slice(),
+            completed: false,
+        }
+    }
+
+    pub fn freqs(&self) -> impl Iterator<Item = Real> + '_ {
+        self.resonators.iter().map(|resonator| resonator.freq)
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block.size
+    }
+
+    /// Process a sample, returning the power of each tone (in the same order
+    /// as the frequencies) at the end of each block.
+    pub fn process_sample(&mut self, sample: Real) -> Option<&[TonePower]> {
+        for resonator in &mut self.resonators[..] {
+            resonator.process_sample(sample);
+        }
+        let energy = self.block.process_sample(sample)?;
+        for (power, resonator) in self.powers.iter_mut().zip(&mut self.resonators[..]) {
+            *power = self.block.tone_power(resonator.finish(), energy);
+        }
+        self.completed = true;
+        Some(&self.powers)
+    }
+
+    /// Index of the strongest tone in the last completed block, or `None`
+    /// until the first block completes.
+    pub fn strongest(&self) -> Option<usize> {
+        if !self.completed {
+            return None;
+        }
+        self.powers
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.absolute.total_cmp(&b.absolute))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{sin, sinc};
+
+    const SAMPLE_RATE: Real = 8000.0;
+
+    fn tone(freq: Real, amplitude: Real, n: usize) -> Real {
+        amplitude * sin(TAU * freq * n as Real / SAMPLE_RATE)
+    }
+
+    #[test]
+    fn detects_tone_on_and_off_bin() {
+        // 40 Hz bins, with the tone on one of them.
+        let block_size = 200;
+        let bin_width = SAMPLE_RATE / block_size as Real;
+        for freq in [1000.0, 1020.0, 1100.0, 1200.0, 700.0] {
+            let mut goertzel = Goertzel::new(freq, SAMPLE_RATE, block_size);
+            let mut powers = Vec::new();
+            for n in 0..3 * block_size {
+                powers.extend(goertzel.process_sample(tone(1000.0, 0.5, n)));
+            }
+            assert_eq!(powers.len(), 3);
+
+            // About the response of a rectangular window, with nulls on the
+            // other bins, and exact on them.
+            let bins = (freq - 1000.0) / bin_width;
+            let response = sinc(bins).powi(2);
+            let tolerance = if bins.fract() == 0.0 { 1e-4 } else { 0.01 };
+            for power in powers {
+                assert!(
+                    (power.relative - response).abs() < tolerance,
+                    "{freq}: {power:?}"
+                );
+                // All of the power is in the tone.
+                assert!((power.absolute - 0.125 * power.relative).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_dtmf() {
+        const ROWS: [Real; 4] = [697.0, 770.0, 852.0, 941.0];
+        const COLUMNS: [Real; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+        // The usual block size for DTMF at 8 kHz, with bins about 39 Hz wide.
+        let block_size = 205;
+        let mut rows = GoertzelBank::new(&ROWS, SAMPLE_RATE, block_size);
+        let mut columns = GoertzelBank::new(&COLUMNS, SAMPLE_RATE, block_size);
+
+        for (digit, (row, column)) in [(1, 1), (0, 3), (3, 2), (2, 0)].into_iter().enumerate() {
+            let mut blocks = 0;
+            for n in 0..2 * block_size {
+                let sample = tone(ROWS[row], 0.4, n) + tone(COLUMNS[column], 0.5, n);
+                let row_powers = rows.process_sample(sample).map(<[_]>::to_vec);
+                let column_powers = columns.process_sample(sample).map(<[_]>::to_vec);
+                if digit == 0 && n < block_size - 1 {
+                    assert_eq!(rows.strongest(), None);
+                }
+                let (Some(row_powers), Some(column_powers)) = (row_powers, column_powers) else {
+                    continue;
+                };
+                blocks += 1;
+                // Every tone is well above the others in its group, which
+                // are at least 1.7 bins away.
+                for (powers, index) in [(row_powers, row), (column_powers, column)] {
+                    for (k, power) in powers.iter().enumerate() {
+                        if k != index {
+                            assert!(power.absolute < 0.05 * powers[index].absolute);
+                        }
+                    }
+                }
+            }
+            assert_eq!(blocks, 2);
+            assert_eq!(rows.strongest(), Some(row));
+            assert_eq!(columns.strongest(), Some(column));
+        }
+    }
+}
@@ -2,9 +2,14 @@ use std::{iter::Sum, ops};
 
 use num_complex::Complex;
 
-use crate::{math::Real, sample::Sample};
+use crate::{filter::kernel, math::Real, sample::Sample};
 
+/// Complex sample.
+///
+/// Laid out as two consecutive [`Real`]s, so that slices of samples can be
+/// handed to SIMD kernels as interleaved values.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
 pub struct IQ {
     pub i: Real,
     pub q: Real,
@@ -126,6 +131,10 @@ impl Sample for IQ {
     fn magnitude(&self) -> Real {
         self.magnitude_squared().sqrt()
     }
+
+    fn weighted_sum(weights: &[Real], samples: &[Self]) -> Self {
+        kernel::iq_dot(weights, samples)
+    }
 }
 
 impl From<Complex<Real>> for IQ {
@@ -133,3 +142,77 @@ impl From<Complex<Real>> for IQ {
         IQ::new(value.re, value.im)
     }
 }
+
+impl From<Real> for IQ {
+    fn from(value: Real) -> Self {
+        IQ::new(value, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{FRAC_PI_2, PI, TAU};
+
+    fn assert_close(a: IQ, b: IQ) {
+        assert!((a - b).magnitude() < 8.0 * Real::EPSILON, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn arithmetic_matches_num_complex() {
+        let values = [
+            IQ::new(1.5, -2.0),
+            IQ::new(-0.25, 0.75),
+            IQ::new(0.0, 1.0),
+            IQ::new(3.0, 0.0),
+        ];
+        for &a in &values {
+            for &b in &values {
+                let (ca, cb) = (Complex::new(a.i, a.q), Complex::new(b.i, b.q));
+                assert_eq!(a + b, IQ::from(ca + cb));
+                assert_eq!(a - b, IQ::from(ca - cb));
+                assert_eq!(a * b, IQ::from(ca * cb));
+                let mut product = a;
+                product *= b;
+                assert_eq!(product, a * b);
+            }
+            assert_eq!(a * 2.0, IQ::from(Complex::new(a.i, a.q) * 2.0));
+            assert_eq!(a / 4.0, IQ::new(a.i / 4.0, a.q / 4.0));
+            assert_eq!(a.conj(), IQ::from(Complex::new(a.i, a.q).conj()));
+        }
+        assert_eq!(values.iter().copied().sum::<IQ>(), IQ::new(4.25, -0.25));
+        assert_eq!(IQ::from(2.0), IQ::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn polar() {
+        // +-PI are left out, as they may come back as either one.
+        for k in -7..8 {
+            let phase = PI * k as Real / 8.0;
+            let z = IQ::new_polar(phase, 2.0);
+            assert!((z.magnitude() - 2.0).abs() < 8.0 * Real::EPSILON);
+            assert!((z.magnitude_squared() - 4.0).abs() < 32.0 * Real::EPSILON);
+            assert!((z.phase() - phase).abs() < 8.0 * Real::EPSILON, "{k}");
+            assert_close(z.unit(), IQ::new_polar(phase, 1.0));
+        }
+        assert_close(IQ::new_polar(FRAC_PI_2, 1.0), IQ::new(0.0, 1.0));
+        assert_eq!(IQ::ZERO.unit(), IQ::ZERO);
+    }
+
+    #[test]
+    fn rotation_accumulates_slowly() {
+        // Repeatedly rotating by a unit phasor, as a recursive oscillator
+        // would, drifts in magnitude and phase only by rounding.
+        let steps = 100_000;
+        let rotation = IQ::new_polar(TAU / 1000.0, 1.0);
+        let mut z = IQ::new(1.0, 0.0);
+        for _ in 0..steps {
+            z *= rotation;
+        }
+        let drift = (z.magnitude() - 1.0).abs();
+        assert!(drift < steps as Real * Real::EPSILON, "{drift}");
+        // After exactly 100 turns.
+        let phase = z.phase();
+        assert!(phase.abs() < steps as Real * Real::EPSILON, "{phase}");
+    }
+}
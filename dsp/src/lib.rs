@@ -1,15 +1,21 @@
+#![cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+
 pub mod agc;
 pub mod buffer;
 pub mod channel;
 pub mod codec;
 pub mod early_late;
+pub mod fft;
 pub mod filter;
+pub mod fixed;
+pub mod goertzel;
 pub mod iq;
 pub mod math;
 pub mod modem;
 pub mod pll;
 pub mod resample;
 pub mod sample;
+pub mod spectrum;
 pub mod wave;
 
 use sample::Sample;
@@ -1,7 +1,16 @@
-use crate::{iq::IQ, sample::Sample};
+use crate::{fft::Fft, filter::kernel, iq::IQ, sample::Sample};
 
+/// The floating-point type used for samples, coefficients and frequencies
+/// throughout the crate.
+///
+/// This is `f32` by default, or `f64` with the `f64` feature, for long-running
+/// or offline processing where the extra precision is worth the speed.
+#[cfg(not(feature = "f64"))]
 pub type Real = f32;
 
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
 impl Sample for Real {
     const ZERO: Self = 0.0;
 
@@ -12,11 +21,20 @@ impl Sample for Real {
     fn magnitude_squared(&self) -> Real {
         self * self
     }
+
+    fn weighted_sum(weights: &[Real], samples: &[Self]) -> Self {
+        kernel::real_dot(weights, samples)
+    }
 }
 
+#[cfg(not(feature = "f64"))]
 #[doc(inline)]
 pub use std::f32::consts::*;
 
+#[cfg(feature = "f64")]
+#[doc(inline)]
+pub use std::f64::consts::*;
+
 /// Wrapper around `Real::sin`.
 ///
 /// This allows the sine function to be imported and written as `sin(PI)`
@@ -33,6 +51,95 @@ pub fn cos(x: Real) -> Real {
     x.cos()
 }
 
+/// Four-quadrant arctangent of `y / x`, like `Real::atan2`, but faster and
+/// approximate.
+///
+/// The angle is folded into the first octant and evaluated with a ninth-order
+/// polynomial (Abramowitz and Stegun, 4.4.49). The maximum error is about
+/// `1.2e-5` radians, which is below the noise of anything coming out of a
+/// 16-bit ADC. Returns 0 for `atan2(0, 0)`.
+pub fn fast_atan2(y: Real, x: Real) -> Real {
+    const COEFFS: [Real; 5] = [0.999866, -0.3302995, 0.180141, -0.085133, 0.0208351];
+
+    let (abs_x, abs_y) = (x.abs(), y.abs());
+    if abs_x == 0.0 && abs_y == 0.0 {
+        return 0.0;
+    }
+
+    let octant_swapped = abs_y > abs_x;
+    let z = if octant_swapped {
+        abs_x / abs_y
+    } else {
+        abs_y / abs_x
+    };
+    let z2 = z * z;
+    let poly = COEFFS
+        .iter()
+        .rev()
+        .fold(0.0, |acc, &coeff| acc * z2 + coeff);
+
+    let mut angle = z * poly;
+    if octant_swapped {
+        angle = FRAC_PI_2 - angle;
+    }
+    if x < 0.0 {
+        angle = PI - angle;
+    }
+    if y < 0.0 {
+        -angle
+    } else {
+        angle
+    }
+}
+
+/// Sine and cosine of `x`, faster and approximate.
+///
+/// The argument is reduced to `[-PI/4, PI/4]` by subtracting the nearest
+/// multiple of `PI/2`, split into three parts (Cody and Waite) to keep the
+/// reduction accurate, and both functions are evaluated there with truncated
+/// Taylor series. The maximum error is about `1.1e-7` for `|x|` up to `1e4`,
+/// close to the rounding error of `f32` itself, growing to about `1e-6` at
+/// `1e5`. With the `f64` feature the series limits it to about `2.5e-8`, so
+/// prefer the exact functions when that matters.
+pub fn fast_sincos(x: Real) -> (Real, Real) {
+    // Each part but the last has few enough significant bits that its
+    // product with any quadrant up to `1e5 * 2 / PI` is exact.
+    #[cfg(not(feature = "f64"))]
+    const PI_2_PARTS: [Real; 3] = [1.5703125, 4.837513e-4, 7.54979e-8];
+    #[cfg(feature = "f64")]
+    const PI_2_PARTS: [Real; 3] = [
+        1.570_796_326_734_125_6,
+        6.077_100_506_303_966e-11,
+        2.022_266_248_711_166_5e-21,
+    ];
+
+    // Round to the nearest quadrant with a truncating cast, which is much
+    // cheaper than `round()` on targets without a rounding instruction.
+    let scaled = x * FRAC_2_PI;
+    let quadrant = (scaled + (0.5 as Real).copysign(scaled)) as i32;
+    let r = PI_2_PARTS
+        .iter()
+        .fold(x, |r, &part| r - quadrant as Real * part);
+    let r2 = r * r;
+
+    let sin = r
+        * (1.0
+            + r2 * (-1.0 / 6.0
+                + r2 * (1.0 / 120.0 + r2 * (-1.0 / 5040.0 + r2 * (1.0 / 362880.0)))));
+    let cos = 1.0 + r2 * (-0.5 + r2 * (1.0 / 24.0 + r2 * (-1.0 / 720.0 + r2 * (1.0 / 40320.0))));
+
+    // Rotate by the quadrant without branching, since the quadrant is often
+    // unpredictable.
+    let (sin, cos) = if quadrant & 1 == 0 {
+        (sin, cos)
+    } else {
+        (cos, sin)
+    };
+    let sin_sign = 1.0 - (quadrant & 2) as Real;
+    let cos_sign = 1.0 - ((quadrant + 1) & 2) as Real;
+    (sin * sin_sign, cos * cos_sign)
+}
+
 /// `sin(PI * x) / (PI * x)` but continuous.
 pub fn sinc(x: Real) -> Real {
     if x == 0.0 {
@@ -42,6 +149,23 @@ pub fn sinc(x: Real) -> Real {
     }
 }
 
+/// Zeroth-order modified Bessel function of the first kind, `I0(x)`.
+///
+/// Evaluated by its power series, which converges quickly for the argument
+/// range used by the Kaiser window.
+pub fn bessel_i0(x: Real) -> Real {
+    let half_x = 0.5 * x;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut k = 1.0;
+    while term > sum * Real::EPSILON {
+        term *= (half_x / k).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
 /// Impulse response of a raised cosine filter.
 pub fn rc(t: Real, rolloff: Real, sps: Real) -> Real {
     let tn = t / sps;
@@ -72,10 +196,286 @@ pub fn rrc(t: Real, rolloff: Real, sps: Real) -> Real {
 }
 
 /// Evaluate the discrete-time Fourier transform at a single frequency.
-pub fn dtft(data: &[Real], sample_rate: Real, freq: Real) -> Real {
+///
+/// Computes `sum(data[n] * exp(-2j * PI * freq * n / sample_rate))`, with
+/// `n` starting from zero. Use [`zoom_fft`] to evaluate many frequencies.
+pub fn dtft<T: Copy + Into<IQ>>(data: &[T], sample_rate: Real, freq: Real) -> IQ {
+    let cycles_per_sample = freq as f64 / sample_rate as f64;
     data.iter()
         .enumerate()
-        .map(|(i, &h)| IQ::new_polar(freq / sample_rate * (i as Real), h))
-        .sum::<IQ>()
-        .magnitude()
+        .map(|(n, &x)| {
+            // Reduce the phase in double precision, so that it stays accurate
+            // for long inputs.
+            let cycles = (cycles_per_sample * n as f64).fract();
+            x.into() * IQ::new_polar(-TAU * cycles as Real, 1.0)
+        })
+        .sum()
+}
+
+/// `z^exponent` for `z` given by its log-magnitude and angle.
+fn polar_power(log_magnitude: f64, angle: f64, exponent: f64) -> IQ {
+    let magnitude = (log_magnitude * exponent).exp();
+    let angle = angle * exponent;
+    IQ::new(
+        (magnitude * angle.cos()) as Real,
+        (magnitude * angle.sin()) as Real,
+    )
+}
+
+/// Chirp-z transform.
+///
+/// Evaluates the z-transform of `data` at `num_points` points on a spiral,
+/// `z[k] = start * ratio^-k`, returning
+/// `X[k] = sum(data[n] * start^-n * ratio^(n * k))`. With
+/// `start = 1` and `ratio = exp(-2j * PI / N)` this is the DFT, but the points
+/// can be any number of arbitrarily spaced frequencies, and can spiral in or
+/// out from the unit circle.
+///
+/// Computed with Bluestein's algorithm, using FFTs of at least
+/// `data.len() + num_points - 1` points.
+pub fn czt<T: Copy + Into<IQ>>(data: &[T], num_points: usize, start: IQ, ratio: IQ) -> Vec<IQ> {
+    assert!(start != IQ::ZERO && ratio != IQ::ZERO);
+    let log_polar = |z: IQ| {
+        let (i, q) = (z.i as f64, z.q as f64);
+        (i.hypot(q).ln(), q.atan2(i))
+    };
+    chirp_z(data, num_points, log_polar(start), log_polar(ratio))
+}
+
+/// [`czt`] with `start` and `ratio` given by their log-magnitudes and angles,
+/// in double precision.
+fn chirp_z<T: Copy + Into<IQ>>(
+    data: &[T],
+    num_points: usize,
+    (start_log, start_angle): (f64, f64),
+    (ratio_log, ratio_angle): (f64, f64),
+) -> Vec<IQ> {
+    if data.is_empty() || num_points == 0 {
+        return vec![IQ::ZERO; num_points];
+    }
+
+    // ratio^(n^2 / 2)
+    let chirp = |n: usize| polar_power(ratio_log, ratio_angle, 0.5 * (n * n) as f64);
+
+    // n * k = (n^2 + k^2 - (k - n)^2) / 2 turns the sum into a convolution of
+    // the modulated input with the conjugate chirp.
+    let size = (data.len() + num_points - 1).next_power_of_two();
+    let fft = Fft::new(size);
+
+    let mut input = vec![IQ::ZERO; size];
+    for (n, (slot, &x)) in input.iter_mut().zip(data).enumerate() {
+        *slot = x.into() * polar_power(start_log, start_angle, -(n as f64)) * chirp(n);
+    }
+
+    let mut kernel = vec![IQ::ZERO; size];
+    for (k, slot) in kernel.iter_mut().take(num_points).enumerate() {
+        *slot = polar_power(ratio_log, ratio_angle, -0.5 * (k * k) as f64);
+    }
+    for n in 1..data.len() {
+        kernel[size - n] = polar_power(ratio_log, ratio_angle, -0.5 * (n * n) as f64);
+    }
+
+    fft.forward(&mut input);
+    fft.forward(&mut kernel);
+    for (x, &h) in input.iter_mut().zip(&kernel) {
+        *x *= h;
+    }
+    fft.inverse(&mut input);
+
+    input
+        .iter()
+        .take(num_points)
+        .enumerate()
+        .map(|(k, &y)| y * chirp(k))
+        .collect()
+}
+
+/// Evaluate the discrete-time Fourier transform at `num_points` equally
+/// spaced frequencies from `start_freq` to `end_freq` inclusive.
+///
+/// The result matches [`dtft`] at each frequency, but is computed with the
+/// [`czt`] in `O((N + M) log(N + M))` time. The frequency spacing can be much
+/// finer than the `sample_rate / data.len()` of a zero-padded FFT would allow
+/// at the same cost, which makes this useful for pinning down the exact
+/// frequency of a carrier or the depth of a filter null.
+///
+/// ```
+/// use k9api_dsp::math::{zoom_fft, Real, TAU};
+/// use k9api_dsp::sample::Sample;
+///
+/// let sample_rate = 8000.0;
+/// let signal: Vec<Real> = (0..4000)
+///     .map(|n| (TAU * 1000.3 * n as Real / sample_rate).cos())
+///     .collect();
+///
+/// // 0.1 Hz steps across 990 to 1010 Hz.
+/// let spectrum = zoom_fft(&signal, sample_rate, 990.0, 1010.0, 201);
+/// let peak = (0..spectrum.len())
+///     .max_by(|&a, &b| spectrum[a].magnitude().total_cmp(&spectrum[b].magnitude()))
+///     .unwrap();
+/// assert_eq!(peak, 103);
+/// ```
+pub fn zoom_fft<T: Copy + Into<IQ>>(
+    data: &[T],
+    sample_rate: Real,
+    start_freq: Real,
+    end_freq: Real,
+    num_points: usize,
+) -> Vec<IQ> {
+    let step = if num_points > 1 {
+        (end_freq - start_freq) as f64 / (num_points - 1) as f64
+    } else {
+        0.0
+    };
+    let start_angle = std::f64::consts::TAU * start_freq as f64 / sample_rate as f64;
+    let step_angle = -std::f64::consts::TAU * step / sample_rate as f64;
+    chirp_z(data, num_points, (0.0, start_angle), (0.0, step_angle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Relative tolerance for results that should be accurate to a few ulps
+    /// of `Real`.
+    const ULPS: Real = 64.0 * Real::EPSILON;
+
+    #[test]
+    fn sinc_values() {
+        assert_eq!(sinc(0.0), 1.0);
+        for k in 1..10 {
+            assert!(sinc(k as Real).abs() < ULPS * k as Real);
+            assert!(sinc(-(k as Real)).abs() < ULPS * k as Real);
+        }
+        assert!((sinc(0.5) - 2.0 / PI).abs() < ULPS);
+    }
+
+    #[test]
+    fn bessel_i0_values() {
+        // From Abramowitz and Stegun, table 9.8, rounded to `Real`.
+        for (x, expected) in [
+            (0.0, 1.0f64),
+            (1.0, 1.2660658777520082),
+            (5.0, 27.239871823604442),
+            (10.0, 2815.716628466254),
+        ] {
+            let expected = expected as Real;
+            assert!((bessel_i0(x) - expected).abs() < ULPS * expected, "{x}");
+        }
+    }
+
+    #[test]
+    fn raised_cosine_is_nyquist() {
+        let sps = 8.0;
+        for rolloff in [0.25, 0.35, 0.5, 1.0] {
+            assert!((rc(0.0, rolloff, sps) * sps - 1.0).abs() < ULPS);
+            for symbol in 1..20 {
+                let t = symbol as Real * sps;
+                assert!(
+                    rc(t, rolloff, sps).abs() < 1e3 * Real::EPSILON,
+                    "{rolloff} {t}"
+                );
+            }
+            // Continuous across the removable singularity at `t = sps / (2 * rolloff)`.
+            let singular = sps / (2.0 * rolloff);
+            let step = 1e-3 * sps;
+            let around =
+                (rc(singular - step, rolloff, sps) + rc(singular + step, rolloff, sps)) / 2.0;
+            assert!((rc(singular, rolloff, sps) - around).abs() < 1e-4 / sps);
+        }
+    }
+
+    #[test]
+    fn root_raised_cosine_squared_is_raised_cosine() {
+        let (sps, rolloff) = (8.0, 0.35);
+        let span = 40 * sps as i32;
+        let rrc_taps: Vec<Real> = (-span..=span)
+            .map(|t| rrc(t as Real, rolloff, sps))
+            .collect();
+        for symbol in 0..4 {
+            let lag = symbol * sps as usize;
+            let autocorrelation: Real = rrc_taps
+                .iter()
+                .zip(&rrc_taps[lag..])
+                .map(|(a, b)| a * b)
+                .sum();
+            let expected = rc(lag as Real, rolloff, sps);
+            assert!((autocorrelation - expected).abs() < 2e-3 / sps, "{symbol}");
+        }
+    }
+
+    #[test]
+    fn fast_atan2_error() {
+        let mut max_error: Real = 0.0;
+        for k in 0..3600 {
+            let angle = -PI + TAU * k as Real / 3600.0;
+            for magnitude in [1e-3, 1.0, 1e4] {
+                let (y, x) = (magnitude * angle.sin(), magnitude * angle.cos());
+                let error = (fast_atan2(y, x) - y.atan2(x)).abs();
+                // The two may wrap to opposite ends of the range at +-PI.
+                max_error = max_error.max(error.min(TAU - error));
+            }
+        }
+        assert!(max_error < 1.3e-5, "{max_error}");
+        assert_eq!(fast_atan2(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn fast_sincos_error() {
+        // The documented limits.
+        #[cfg(not(feature = "f64"))]
+        let bounds = [(1e4, 1.1e-7), (1e5, 1e-6)];
+        #[cfg(feature = "f64")]
+        let bounds = [(1e4, 2.5e-8), (1e5, 2.5e-8)];
+
+        for (limit, bound) in bounds {
+            let steps = 1_000_000;
+            let mut max_error: f64 = 0.0;
+            for k in -steps..=steps {
+                let x = limit * k as Real / steps as Real;
+                let (sin, cos) = fast_sincos(x);
+                let exact = (x as f64).sin_cos();
+                max_error = max_error
+                    .max((sin as f64 - exact.0).abs())
+                    .max((cos as f64 - exact.1).abs());
+            }
+            assert!(max_error < bound, "|x| up to {limit}: {max_error}");
+        }
+    }
+
+    #[test]
+    fn dtft_of_long_tone() {
+        // Long enough that the phase would lose precision if it were
+        // accumulated in `f32`.
+        // A bin spacing of 1/16 Hz, so that the bins are exact in `f32`.
+        let (sample_rate, freq) = (65536.0, 1234.5);
+        let len = 1 << 20;
+        let tone: Vec<IQ> = (0..len)
+            .map(|n| {
+                let cycles = (freq as f64 * n as f64 / sample_rate as f64).fract();
+                IQ::new_polar(TAU * cycles as Real, 1.0)
+            })
+            .collect();
+        let peak = dtft(&tone, sample_rate, freq) / len as Real;
+        assert!((peak - IQ::new(1.0, 0.0)).magnitude() < 1e-4);
+        // The next bin over is a null.
+        let off_peak = dtft(&tone, sample_rate, freq + 0.0625) / len as Real;
+        assert!(off_peak.magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_fft_matches_dtft() {
+        let sample_rate = 8000.0;
+        let signal: Vec<Real> = (0..500)
+            .map(|n| sin(TAU * 1003.7 / sample_rate * n as Real) + 0.3 * cos(0.1 * n as Real))
+            .collect();
+        let spectrum = zoom_fft(&signal, sample_rate, 900.0, 1100.0, 41);
+        for (k, &bin) in spectrum.iter().enumerate() {
+            let expected = dtft(&signal, sample_rate, 900.0 + 5.0 * k as Real);
+            assert!(
+                (bin - expected).magnitude() < 1e-3 * signal.len() as Real,
+                "{k}"
+            );
+        }
+    }
 }
@@ -1,4 +1,8 @@
-use crate::{iq::IQ, math::Real, sample::Sample};
+use crate::{
+    iq::IQ,
+    math::{fast_atan2, Real},
+    sample::Sample,
+};
 
 /// Demodulates FM from baseband IQ samples.
 ///
@@ -7,15 +11,32 @@ use crate::{iq::IQ, math::Real, sample::Sample};
 /// would just alter the amplitude of the output.
 pub struct FmDemod {
     last: IQ,
+    fast_trig: bool,
 }
 
 impl FmDemod {
     pub fn new() -> Self {
-        Self { last: IQ::ZERO }
+        Self {
+            last: IQ::ZERO,
+            fast_trig: false,
+        }
+    }
+
+    /// Like [`new`](Self::new), but measuring the phase with [`fast_atan2`].
+    pub fn fast() -> Self {
+        Self {
+            fast_trig: true,
+            ..Self::new()
+        }
     }
 
     pub fn next(&mut self, sample: IQ) -> Real {
-        let output = (sample * self.last.conj()).phase();
+        let delta = sample * self.last.conj();
+        let output = if self.fast_trig {
+            fast_atan2(delta.q, delta.i)
+        } else {
+            delta.phase()
+        };
         self.last = sample;
         output
     }
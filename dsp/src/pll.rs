@@ -1,9 +1,16 @@
-use crate::{filter::Fir, iq::IQ, math::Real, wave::Oscillator};
+use crate::{
+    filter::{Filter, Fir},
+    iq::IQ,
+    math::{Real, TAU},
+    wave::{LocalOscillator, Oscillator},
+};
 
-pub struct Costas {
+/// Costas loop, generic over the local oscillator and the loop filter, which
+/// may be any [`Filter`] of IQ samples.
+pub struct Costas<O = Oscillator, F = Fir<IQ>> {
     k: Real,
-    osc: Oscillator,
-    filter: Fir<IQ>,
+    osc: O,
+    filter: F,
     phase_offset: Real,
 }
 
@@ -13,11 +20,19 @@ pub struct Output {
     pub error: Real,
 }
 
-impl Costas {
-    pub fn new(carrier_freq: Real, k: Real, filter: Fir<IQ>) -> Self {
+impl<F: Filter<IQ>> Costas<Oscillator, F> {
+    pub fn new(carrier_freq: Real, k: Real, filter: F) -> Self {
+        Self::with_oscillator(Oscillator::new(1.0 / carrier_freq, 0.0), k, filter)
+    }
+}
+
+impl<O: LocalOscillator, F: Filter<IQ>> Costas<O, F> {
+    /// Construct a loop around any local oscillator, such as an
+    /// [`Nco`](crate::wave::Nco), already tuned to the carrier frequency.
+    pub fn with_oscillator(osc: O, k: Real, filter: F) -> Self {
         Self {
             k,
-            osc: Oscillator::new(1.0 / carrier_freq, 0.0),
+            osc,
             filter,
             phase_offset: 0.0,
         }
@@ -25,10 +40,28 @@ impl Costas {
 
     pub fn process(&mut self, sample: Real) -> Output {
         let carrier = self.osc.next_with_offset(self.phase_offset);
-        let baseband = self.filter.process_sample(carrier * sample);
+        self.track(carrier, carrier * sample)
+    }
+
+    /// Process a complex input sample, such as the output of
+    /// [`AnalyticSignal`](crate::filter::hilbert::AnalyticSignal).
+    ///
+    /// The input is conjugated before mixing so that the loop locks with the
+    /// same phase convention as `process`, but without the image at twice the
+    /// carrier frequency that the loop filter would otherwise have to reject.
+    pub fn process_iq(&mut self, sample: IQ) -> Output {
+        let carrier = self.osc.next_with_offset(self.phase_offset);
+        self.track(carrier, carrier * sample.conj())
+    }
+
+    fn track(&mut self, carrier: IQ, mixed: IQ) -> Output {
+        let baseband = self.filter.process_sample(mixed);
         let bnorm = baseband.unit();
         let error = bnorm.i * bnorm.q;
-        self.phase_offset -= self.k * error;
+        // Keep the offset within one turn, since a frequency offset makes it
+        // grow without bound and the oscillator (notably with `fast_sincos`)
+        // loses accuracy for large angles.
+        self.phase_offset = (self.phase_offset - self.k * error) % TAU;
         Output {
             baseband,
             carrier,
@@ -0,0 +1,401 @@
+//! Cascaded integrator-comb (CIC) decimation and interpolation.
+
+use crate::{
+    filter::{kernel::FirSample, Fir, Window},
+    fixed::{FixedPoint, IQ15, IQ31, Q15, Q31},
+    iq::IQ,
+    math::{cos, sinc, Real, TAU},
+    sample::Sample,
+};
+
+/// Sample types that can be converted to the fixed-point representation used
+/// by the CIC integrators and combs.
+///
+/// Implemented for [`Real`] and [`IQ`], and for the [fixed-point
+/// types](crate::fixed), whose raw integers are shifted into the registers
+/// without going through floating point. Fixed-point outputs are rounded to
+/// nearest and saturate.
+pub trait CicSample: Sample {
+    /// One integer per real component of the sample.
+    type Fixed: Copy + Default + AsRef<[i64]> + AsMut<[i64]>;
+
+    fn to_fixed(self, scale: f64) -> Self::Fixed;
+
+    fn from_fixed(value: Self::Fixed, scale: f64) -> Self;
+}
+
+impl CicSample for Real {
+    type Fixed = [i64; 1];
+
+    fn to_fixed(self, scale: f64) -> Self::Fixed {
+        [(self as f64 * scale).round() as i64]
+    }
+
+    fn from_fixed(value: Self::Fixed, scale: f64) -> Self {
+        (value[0] as f64 / scale) as Real
+    }
+}
+
+impl CicSample for IQ {
+    type Fixed = [i64; 2];
+
+    fn to_fixed(self, scale: f64) -> Self::Fixed {
+        [
+            (self.i as f64 * scale).round() as i64,
+            (self.q as f64 * scale).round() as i64,
+        ]
+    }
+
+    fn from_fixed(value: Self::Fixed, scale: f64) -> Self {
+        IQ::new(
+            (value[0] as f64 / scale) as Real,
+            (value[1] as f64 / scale) as Real,
+        )
+    }
+}
+
+macro_rules! cic_fixed {
+    ($real:ident, $complex:ident, $int:ty) => {
+        impl CicSample for $real {
+            type Fixed = [i64; 1];
+
+            fn to_fixed(self, scale: f64) -> Self::Fixed {
+                let scale = scale / (Self::FRACTION_BITS as f64).exp2();
+                [(self.0 as f64 * scale).round() as i64]
+            }
+
+            fn from_fixed(value: Self::Fixed, scale: f64) -> Self {
+                let scale = scale / (Self::FRACTION_BITS as f64).exp2();
+                // Float to integer casts saturate.
+                Self((value[0] as f64 / scale).round() as $int)
+            }
+        }
+
+        impl CicSample for $complex {
+            type Fixed = [i64; 2];
+
+            fn to_fixed(self, scale: f64) -> Self::Fixed {
+                [self.i.to_fixed(scale)[0], self.q.to_fixed(scale)[0]]
+            }
+
+            fn from_fixed(value: Self::Fixed, scale: f64) -> Self {
+                Self::new(
+                    $real::from_fixed([value[0]], scale),
+                    $real::from_fixed([value[1]], scale),
+                )
+            }
+        }
+    };
+}
+
+cic_fixed!(Q15, IQ15, i16);
+cic_fixed!(Q31, IQ31, i32);
+
+/// Parameters of a CIC filter.
+///
+/// A CIC filter is a cascade of `stages` integrators running at the high rate
+/// and `stages` combs (with a delay of `differential_delay` low-rate samples)
+/// running at the low rate, with a rate change of `factor` between them. It
+/// needs no multiplications, but its response droops across the passband;
+/// see [`CicCompensation`].
+#[derive(Debug, Clone, Copy)]
+pub struct Cic {
+    pub factor: usize,
+    pub stages: usize,
+    pub differential_delay: usize,
+}
+
+impl Cic {
+    /// DC gain of the integrators and combs, `(factor * differential_delay) ^ stages`.
+    ///
+    /// The decimator and interpolator divide this out, so their DC gain is 1.
+    pub fn gain(&self) -> f64 {
+        ((self.factor * self.differential_delay) as f64).powi(self.stages as i32)
+    }
+
+    /// Number of bits the integer registers grow by.
+    pub fn bit_growth(&self) -> u32 {
+        self.gain().log2().ceil() as u32
+    }
+
+    /// Normalized magnitude response at `freq`, where `sample_rate` is the
+    /// low (decimated) sample rate.
+    pub fn magnitude(&self, freq: Real, sample_rate: Real) -> Real {
+        let x = freq / sample_rate;
+        let comb = sinc(self.differential_delay as Real * x);
+        let integrator = sinc(x / self.factor as Real);
+        (comb / integrator).abs().powi(self.stages as i32)
+    }
+
+    /// Scale from samples to fixed point.
+    ///
+    /// Samples are quantized so that the registers leave 8 bits of headroom
+    /// (input magnitudes up to 256) after the bit growth, with at most 32
+    /// fractional bits.
+    fn scale(&self) -> f64 {
+        let bit_growth = self.bit_growth();
+        assert!(
+            bit_growth <= 38,
+            "CIC bit growth of {bit_growth} bits is too large"
+        );
+        let fraction_bits = (54 - bit_growth).min(32);
+        (fraction_bits as f64).exp2()
+    }
+
+    fn validate(&self) {
+        assert!(self.factor > 0);
+        assert!(self.stages > 0);
+        assert!(self.differential_delay > 0);
+    }
+}
+
+/// Run `input` through the integrators, returning the last one's output.
+fn integrate<F: AsRef<[i64]> + AsMut<[i64]> + Copy>(integrators: &mut [F], input: &F) -> F {
+    let mut value = *input;
+    for integrator in integrators {
+        for (acc, &x) in integrator.as_mut().iter_mut().zip(value.as_ref()) {
+            *acc = acc.wrapping_add(x);
+        }
+        value = *integrator;
+    }
+    value
+}
+
+/// Run `input` through the combs, where `delay_line` holds the last
+/// `differential_delay` inputs of each stage, oldest first at `position`.
+fn comb<F: AsRef<[i64]> + AsMut<[i64]> + Copy>(
+    delay_line: &mut [F],
+    differential_delay: usize,
+    position: usize,
+    input: &F,
+) -> F {
+    let mut value = *input;
+    for stage in delay_line.chunks_mut(differential_delay) {
+        let delayed = std::mem::replace(&mut stage[position], value);
+        for (x, &d) in value.as_mut().iter_mut().zip(delayed.as_ref()) {
+            *x = x.wrapping_sub(d);
+        }
+    }
+    value
+}
+
+/// Decimate by an integer factor using a CIC filter.
+///
+/// The arithmetic is done in wrapping 64-bit integers, which gives exact
+/// results as long as the output itself fits, no matter how large the
+/// integrators grow.
+pub struct CicDecimator<T: CicSample = Real> {
+    design: Cic,
+    integrators: Box<[T::Fixed]>,
+    delay_line: Box<[T::Fixed]>,
+    position: usize,
+    scale: f64,
+}
+
+impl<T: CicSample> CicDecimator<T> {
+    pub fn new(design: Cic) -> Self {
+        design.validate();
+        Self {
+            integrators: vec![T::Fixed::default(); design.stages].into_boxed_slice(),
+            delay_line: vec![T::Fixed::default(); design.stages * design.differential_delay]
+                .into_boxed_slice(),
+            position: 0,
+            scale: design.scale(),
+            design,
+        }
+    }
+
+    pub fn design(&self) -> &Cic {
+        &self.design
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len() * self.design.factor);
+        let output_scale = self.scale * self.design.gain();
+        for (inp, out) in input.chunks(self.design.factor).zip(output) {
+            let mut integrated = T::Fixed::default();
+            for &sample in inp {
+                integrated = integrate(&mut self.integrators, &sample.to_fixed(self.scale));
+            }
+            let combed = comb(
+                &mut self.delay_line,
+                self.design.differential_delay,
+                self.position,
+                &integrated,
+            );
+            self.position = (self.position + 1) % self.design.differential_delay;
+            *out = T::from_fixed(combed, output_scale);
+        }
+    }
+}
+
+/// Interpolate by an integer factor using a CIC filter.
+///
+/// Like [`CicDecimator`], this uses wrapping 64-bit integer arithmetic. The
+/// gain is normalized so that a constant input produces the same constant
+/// output, as with [`Upsample`](super::Upsample).
+pub struct CicInterpolator<T: CicSample = Real> {
+    design: Cic,
+    integrators: Box<[T::Fixed]>,
+    delay_line: Box<[T::Fixed]>,
+    position: usize,
+    scale: f64,
+}
+
+impl<T: CicSample> CicInterpolator<T> {
+    pub fn new(design: Cic) -> Self {
+        design.validate();
+        Self {
+            integrators: vec![T::Fixed::default(); design.stages].into_boxed_slice(),
+            delay_line: vec![T::Fixed::default(); design.stages * design.differential_delay]
+                .into_boxed_slice(),
+            position: 0,
+            scale: design.scale(),
+            design,
+        }
+    }
+
+    pub fn design(&self) -> &Cic {
+        &self.design
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len() * self.design.factor, output.len());
+        // Zero stuffing divides the DC gain by the rate change.
+        let output_scale = self.scale * self.design.gain() / self.design.factor as f64;
+        for (out, &inp) in output.chunks_mut(self.design.factor).zip(input) {
+            let combed = comb(
+                &mut self.delay_line,
+                self.design.differential_delay,
+                self.position,
+                &inp.to_fixed(self.scale),
+            );
+            self.position = (self.position + 1) % self.design.differential_delay;
+
+            // The comb output is followed by `factor - 1` zeros.
+            let mut value = combed;
+            for slot in out {
+                let integrated = integrate(&mut self.integrators, &value);
+                *slot = T::from_fixed(integrated, output_scale);
+                value = T::Fixed::default();
+            }
+        }
+    }
+}
+
+/// Designs an FIR filter that flattens the passband droop of a CIC filter.
+///
+/// The filter runs at the low sample rate (after a [`CicDecimator`] or before
+/// a [`CicInterpolator`]). Its desired response is the inverse of the CIC
+/// response up to `cutoff`, and zero above it; the impulse response is found
+/// by integrating that over frequency, then tapered by `window`.
+#[derive(Debug, Clone, Copy)]
+pub struct CicCompensation {
+    pub cic: Cic,
+    /// The low sample rate, in Hz.
+    pub sample_rate: Real,
+    pub cutoff: Real,
+    pub num_taps: usize,
+    pub window: Window,
+}
+
+impl CicCompensation {
+    pub fn num_taps(&self) -> usize {
+        // Ensure num_taps is odd
+        self.num_taps | 1
+    }
+
+    pub fn taps(&self) -> Box<[Real]> {
+        assert!(0.0 < self.cutoff && self.cutoff < 0.5 * self.sample_rate);
+        let num_taps = self.num_taps();
+        let center = (num_taps / 2) as Real;
+        let span = (num_taps - 1).max(1) as Real;
+
+        // Midpoint rule over the passband, fine enough to resolve the fastest
+        // cosine in the impulse response.
+        let steps = 32 * num_taps;
+        let step = self.cutoff / steps as Real;
+        let desired: Vec<(Real, Real)> = (0..steps)
+            .map(|k| {
+                let freq = (k as Real + 0.5) * step;
+                (freq, 1.0 / self.cic.magnitude(freq, self.sample_rate))
+            })
+            .collect();
+
+        (0..num_taps)
+            .map(|x| {
+                let n = x as Real - center;
+                let integral: Real = desired
+                    .iter()
+                    .map(|&(freq, gain)| gain * cos(TAU * freq / self.sample_rate * n))
+                    .sum();
+                2.0 * step / self.sample_rate * integral * self.window.sample(x as Real, span)
+            })
+            .collect()
+    }
+
+    pub fn build<T: FirSample>(&self) -> Fir<T> {
+        Fir::new(self.taps())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESIGN: Cic = Cic {
+        factor: 8,
+        stages: 4,
+        differential_delay: 1,
+    };
+
+    fn signal(len: usize) -> Vec<Real> {
+        (0..len)
+            .map(|n| 0.9 * (0.05 * n as Real).sin() * (0.003 * n as Real).cos())
+            .collect()
+    }
+
+    #[test]
+    fn fixed_point_decimator_matches_real() {
+        let input = signal(800);
+        let mut expected = vec![0.0; input.len() / DESIGN.factor];
+        CicDecimator::<Real>::new(DESIGN).process(&input, &mut expected);
+
+        let input: Vec<IQ15> = input
+            .iter()
+            .map(|&x| IQ15::new(Q15::from_real(x), Q15::from_real(-x)))
+            .collect();
+        let mut output = vec![IQ15::ZERO; expected.len()];
+        CicDecimator::new(DESIGN).process(&input, &mut output);
+
+        for (out, &exp) in output.iter().zip(&expected) {
+            // Half an LSB from quantizing the input, and half from the output.
+            assert!((out.i.to_real() - exp).abs() <= 1.0 / 32768.0);
+            assert!((out.q.to_real() + exp).abs() <= 1.0 / 32768.0);
+        }
+    }
+
+    #[test]
+    fn fixed_point_interpolator_matches_real() {
+        let input = signal(100);
+        let mut expected = vec![0.0; input.len() * DESIGN.factor];
+        CicInterpolator::<Real>::new(DESIGN).process(&input, &mut expected);
+
+        let input: Vec<Q31> = input.iter().map(|&x| Q31::from_real(x)).collect();
+        let mut output = vec![Q31::ZERO; expected.len()];
+        CicInterpolator::new(DESIGN).process(&input, &mut output);
+
+        for (out, &exp) in output.iter().zip(&expected) {
+            assert!((out.to_real() - exp).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn full_scale_fixed_point_does_not_wrap() {
+        for value in [Q15::MAX, Q15::MIN] {
+            let mut output = [Q15::ZERO; 8];
+            CicDecimator::new(DESIGN).process(&[value; 64], &mut output);
+            assert_eq!(output[7], value);
+        }
+    }
+}
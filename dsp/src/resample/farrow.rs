@@ -0,0 +1,107 @@
+//! Arbitrary-ratio resampling with a Farrow fractional-delay interpolator.
+
+use crate::{math::Real, sample::Sample};
+
+/// Resample by an arbitrary (possibly irrational, or slowly changing) ratio.
+///
+/// Each output is interpolated between the input samples around it with a
+/// cubic Lagrange polynomial, evaluated in Farrow form so that the fractional
+/// position can change on every output without recomputing any taps. The
+/// output lags the input by about two input samples.
+///
+/// There is no anti-aliasing filter, so when reducing the sample rate the
+/// input should already be band-limited to below the new Nyquist frequency,
+/// e.g. with a [`Downsample`](super::Downsample) or [`Rational`](super::Rational)
+/// stage doing most of the rate change. The interpolation is also most
+/// accurate well below the input's Nyquist frequency.
+pub struct Farrow<T = Real> {
+    history: [T; 4],
+    step: f64,
+    offset: f64,
+}
+
+impl<T: Sample> Farrow<T> {
+    pub fn new(input_rate: Real, output_rate: Real) -> Self {
+        let mut farrow = Self {
+            history: [T::ZERO; 4],
+            step: 1.0,
+            offset: 0.0,
+        };
+        farrow.set_rates(input_rate, output_rate);
+        farrow
+    }
+
+    /// Change the resampling ratio, keeping the current position, e.g. to
+    /// track a drifting clock.
+    pub fn set_rates(&mut self, input_rate: Real, output_rate: Real) {
+        assert!(input_rate > 0.0 && output_rate > 0.0);
+        self.step = input_rate as f64 / output_rate as f64;
+    }
+
+    /// Ratio of the output rate to the input rate.
+    pub fn ratio(&self) -> f64 {
+        1.0 / self.step
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut Vec<T>) {
+        for &sample in input {
+            self.history.copy_within(1.., 0);
+            self.history[3] = sample;
+
+            // `offset` is the position of the next output after
+            // `history[1]`, in input samples.
+            while self.offset < 1.0 {
+                output.push(self.interpolate(self.offset as Real));
+                self.offset += self.step;
+            }
+            self.offset -= 1.0;
+        }
+    }
+
+    fn interpolate(&self, mu: Real) -> T {
+        let [x0, x1, x2, x3] = self.history;
+        let a3 = (x3 - x0) * (1.0 / 6.0) + (x1 - x2) * 0.5;
+        let a2 = (x0 + x2) * 0.5 - x1;
+        let a1 = x2 - x0 * (1.0 / 3.0) - x1 * 0.5 - x3 * (1.0 / 6.0);
+        ((a3 * mu + a2) * mu + a1) * mu + x1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{iq::IQ, math::TAU};
+
+    #[test]
+    fn resampled_tone() {
+        // A tone at 2 kHz, well below the 24 kHz Nyquist frequency of the input.
+        let input_rate = 48000.0;
+        let cycles_per_sample = 2000.0 / 48000.0;
+        let tone = |time: f64| {
+            let cycles = (cycles_per_sample * time).rem_euclid(1.0);
+            IQ::new_polar(TAU * cycles as Real, 1.0)
+        };
+        let input: Vec<IQ> = (0..4800).map(|n| tone(n as f64)).collect();
+        for output_rate in [44100.0, 48000.0, 22050.0, 96000.0 * 1.001] {
+            let mut farrow = Farrow::new(input_rate, output_rate);
+            let mut output = Vec::new();
+            for chunk in input.chunks(333) {
+                farrow.process(chunk, &mut output);
+            }
+            let step = 1.0 / farrow.ratio();
+            let expected_len = (input.len() as f64 / step).ceil() as usize;
+            assert!(output.len().abs_diff(expected_len) <= 1, "{output_rate}");
+
+            // Output `m` is at `m * step` input samples, two samples late.
+            let mut max_error: Real = 0.0;
+            for (m, &sample) in output.iter().enumerate().skip(8) {
+                let expected = tone(m as f64 * step - 2.0);
+                max_error = max_error.max((sample - expected).magnitude());
+            }
+            // The error of cubic interpolation of a tone at `w` radians per
+            // sample peaks at about `w^4 * 9 / 16 / 24` halfway between the
+            // middle samples, `1.1e-4` here.
+            assert!(max_error < 1.2e-4, "{output_rate}: error {max_error}");
+        }
+    }
+}
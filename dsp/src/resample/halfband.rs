@@ -0,0 +1,322 @@
+//! Half-band filters for efficient decimation and interpolation by two.
+
+use crate::{
+    filter::{kernel::FirSample, Fir, Window},
+    math::{sinc, Real, TAU},
+    sample::Sample,
+};
+
+/// Windowed-sinc half-band lowpass filter design.
+///
+/// A half-band filter has its cutoff at a quarter of the sample rate, so its
+/// response is symmetric around that point and every other tap (except the
+/// center tap, which is 0.5) is exactly zero. The number of taps is rounded up
+/// to the next `4k + 3` so that the outermost taps are nonzero.
+#[derive(Debug, Clone, Copy)]
+pub struct HalfBand {
+    pub num_taps: usize,
+    pub window: Window,
+}
+
+impl HalfBand {
+    /// Design with a Kaiser window, with the number of taps estimated from
+    /// the transition width (centered on `sample_rate / 4`) and the stopband
+    /// attenuation in dB.
+    ///
+    /// `sample_rate` is the higher of the two rates.
+    pub fn kaiser(sample_rate: Real, transition_width: Real, attenuation: Real) -> Self {
+        let transition_angle = TAU * transition_width / sample_rate;
+        let estimate = (attenuation - 7.95) / (2.285 * transition_angle) + 1.0;
+        Self {
+            num_taps: estimate.ceil().max(3.0) as usize,
+            window: Window::kaiser(attenuation),
+        }
+    }
+
+    pub fn num_taps(&self) -> usize {
+        // Ensure num_taps is 4k + 3
+        (self.num_taps.max(3) + 1).next_multiple_of(4) - 1
+    }
+
+    /// Delay of the filter at the higher sample rate, i.e. the index of the
+    /// center tap.
+    pub fn delay(&self) -> usize {
+        self.num_taps() / 2
+    }
+
+    pub fn taps(&self) -> Box<[Real]> {
+        let num_taps = self.num_taps();
+        let center = self.delay() as isize;
+        let span = (num_taps - 1) as Real;
+
+        (0..num_taps)
+            .map(|x| {
+                let n = x as isize - center;
+                if n != 0 && n % 2 == 0 {
+                    0.0
+                } else {
+                    0.5 * sinc(0.5 * n as Real) * self.window.sample(x as Real, span)
+                }
+            })
+            .collect()
+    }
+
+    pub fn build<T: FirSample>(&self) -> Fir<T> {
+        Fir::new(self.taps())
+    }
+
+    /// The nonzero taps other than the center tap, i.e. the taps at even
+    /// indices.
+    fn outer_taps(&self) -> Vec<Real> {
+        self.taps().iter().step_by(2).copied().collect()
+    }
+}
+
+/// Delay line returning each sample `len - 1` samples after it was pushed.
+#[derive(Clone)]
+struct Delay<T> {
+    buffer: Box<[T]>,
+    position: usize,
+}
+
+impl<T: Sample> Delay<T> {
+    fn new(delay: usize) -> Self {
+        Self {
+            buffer: vec![T::ZERO; delay + 1].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    fn process_sample(&mut self, sample: T) -> T {
+        self.buffer[self.position] = sample;
+        self.position = (self.position + 1) % self.buffer.len();
+        self.buffer[self.position]
+    }
+}
+
+/// Decimate by two with a half-band filter.
+///
+/// The input is split into its even and odd samples. One of them is filtered
+/// by the nonzero outer taps, and the other only needs delaying and scaling
+/// by the center tap, so each output costs about a quarter of the
+/// multiplications of filtering at the input rate.
+#[derive(Clone)]
+pub struct HalfBandDecimator<T = Real> {
+    outer: Fir<T>,
+    center: Delay<T>,
+}
+
+impl<T: FirSample> HalfBandDecimator<T> {
+    pub fn new(design: HalfBand) -> Self {
+        Self {
+            outer: Fir::new(design.outer_taps()),
+            // Center tap index is odd, 2k + 1, so it applies to the older
+            // sample of each pair delayed by k pairs.
+            center: Delay::new(design.delay() / 2),
+        }
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len() * 2);
+        for (pair, out) in input.chunks_exact(2).zip(output) {
+            *out = self.outer.process_sample(pair[1]) + self.center.process_sample(pair[0]) * 0.5;
+        }
+    }
+}
+
+/// Interpolate by two with a half-band filter.
+///
+/// Every other output is a delayed copy of the input, and the rest are
+/// filtered by the nonzero outer taps, so the zero taps are never multiplied.
+/// Like [`Upsample`](super::Upsample), the gain is doubled to compensate for
+/// the inserted zeros.
+#[derive(Clone)]
+pub struct HalfBandInterpolator<T = Real> {
+    outer: Fir<T>,
+    center: Delay<T>,
+}
+
+impl<T: FirSample> HalfBandInterpolator<T> {
+    pub fn new(design: HalfBand) -> Self {
+        let outer: Vec<Real> = design.outer_taps().iter().map(|tap| 2.0 * tap).collect();
+        Self {
+            outer: Fir::new(outer),
+            center: Delay::new(design.delay() / 2),
+        }
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len() * 2, output.len());
+        for (pair, &inp) in output.chunks_exact_mut(2).zip(input) {
+            pair[0] = self.outer.process_sample(inp);
+            pair[1] = self.center.process_sample(inp);
+        }
+    }
+}
+
+/// Decimate by a power of two with a chain of half-band decimators.
+///
+/// Each stage runs at half the rate of the one before it, so the total cost
+/// is dominated by the first stage. Since the transition band of each stage
+/// may overlap with what the following stages remove, the early stages can
+/// be much shorter than the last one.
+#[derive(Clone)]
+pub struct HalfBandCascade<T = Real> {
+    stages: Box<[HalfBandDecimator<T>]>,
+    scratch: Vec<T>,
+}
+
+impl<T: FirSample> HalfBandCascade<T> {
+    /// Construct a cascade with one stage per design, in order from the
+    /// highest sample rate to the lowest.
+    pub fn new(designs: impl IntoIterator<Item = HalfBand>) -> Self {
+        let stages: Box<[_]> = designs.into_iter().map(HalfBandDecimator::new).collect();
+        assert!(!stages.is_empty());
+        Self {
+            stages,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Total decimation factor, `2 ^ stages`.
+    pub fn factor(&self) -> usize {
+        1 << self.stages.len()
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len() * self.factor());
+        let (last, rest) = self.stages.split_last_mut().unwrap();
+
+        // Each intermediate result is written after its input in the scratch
+        // buffer, which needs at most `input.len()` samples in total.
+        self.scratch.resize(input.len(), T::ZERO);
+        let mut start = 0;
+        let mut len = input.len();
+        for (i, stage) in rest.iter_mut().enumerate() {
+            let half = len / 2;
+            if i == 0 {
+                stage.process(input, &mut self.scratch[..half]);
+            } else {
+                let (done, remaining) = self.scratch.split_at_mut(start + len);
+                stage.process(&done[start..], &mut remaining[..half]);
+                start += len;
+            }
+            len = half;
+        }
+        let last_input = if rest.is_empty() {
+            input
+        } else {
+            &self.scratch[start..start + len]
+        };
+        last.process(last_input, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resample::{Downsample, Upsample};
+
+    fn designs() -> [HalfBand; 4] {
+        [
+            HalfBand {
+                num_taps: 3,
+                window: Window::Rectangular,
+            },
+            HalfBand {
+                num_taps: 12,
+                window: Window::HAMMING,
+            },
+            HalfBand::kaiser(48000.0, 4000.0, 60.0),
+            HalfBand::kaiser(8000.0, 200.0, 90.0),
+        ]
+    }
+
+    fn signal(len: usize) -> Vec<Real> {
+        (0..len)
+            .map(|n| ((n * 7919) % 31) as Real / 31.0 - 0.5)
+            .collect()
+    }
+
+    fn assert_close(actual: &[Real], expected: &[Real]) {
+        assert_eq!(actual.len(), expected.len());
+        for (n, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-5, "sample {n}: {a} != {e}");
+        }
+    }
+
+    #[test]
+    fn every_other_tap_is_zero() {
+        for design in designs() {
+            let taps = design.taps();
+            let center = design.delay();
+            assert_eq!(taps.len() % 4, 3);
+            assert_eq!(center, taps.len() / 2);
+            assert_eq!(taps[center], 0.5);
+            for (n, &tap) in taps.iter().enumerate() {
+                let mirror = taps[taps.len() - 1 - n];
+                assert!((tap - mirror).abs() < 1e-6, "tap {n}: {tap} != {mirror}");
+                if n % 2 == 0 {
+                    assert_ne!(tap, 0.0, "tap {n}");
+                } else if n != center {
+                    assert_eq!(tap, 0.0, "tap {n}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decimator_matches_downsample() {
+        let input = signal(200);
+        for design in designs() {
+            let mut decimator = HalfBandDecimator::new(design);
+            let mut output = vec![0.0; input.len() / 2];
+            let (first, second) = output.split_at_mut(37);
+            decimator.process(&input[..74], first);
+            decimator.process(&input[74..], second);
+
+            let mut expected = vec![0.0; input.len() / 2];
+            Downsample::new(2, design.build()).process(&input, &mut expected);
+            assert_close(&output, &expected);
+        }
+    }
+
+    #[test]
+    fn interpolator_matches_upsample() {
+        let input = signal(100);
+        for design in designs() {
+            let mut interpolator = HalfBandInterpolator::new(design);
+            let mut output = vec![0.0; input.len() * 2];
+            let (first, second) = output.split_at_mut(74);
+            interpolator.process(&input[..37], first);
+            interpolator.process(&input[37..], second);
+
+            let mut expected = vec![0.0; input.len() * 2];
+            Upsample::new(2, design.build()).process(&input, &mut expected);
+            assert_close(&output, &expected);
+        }
+    }
+
+    #[test]
+    fn cascade_matches_chained_decimators() {
+        let input = signal(960);
+        let designs = designs();
+        for stages in 1..=designs.len() {
+            let mut cascade = HalfBandCascade::new(designs[..stages].iter().copied());
+            assert_eq!(cascade.factor(), 1 << stages);
+            let mut output = vec![0.0; input.len() / cascade.factor()];
+            let split = output.len() / 3;
+            let (first, second) = output.split_at_mut(split);
+            cascade.process(&input[..split * cascade.factor()], first);
+            cascade.process(&input[split * cascade.factor()..], second);
+
+            let mut expected = input.clone();
+            for &design in &designs[..stages] {
+                let mut decimated = vec![0.0; expected.len() / 2];
+                HalfBandDecimator::new(design).process(&expected, &mut decimated);
+                expected = decimated;
+            }
+            assert_close(&output, &expected);
+        }
+    }
+}
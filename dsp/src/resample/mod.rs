@@ -0,0 +1,284 @@
+use crate::filter::kernel::{DelayLine, FirSample};
+use crate::filter::Fir;
+use crate::math::Real;
+
+pub mod cic;
+pub mod farrow;
+pub mod halfband;
+
+/// Split an impulse response into `factor` polyphase components.
+///
+/// Component `p` holds taps `p, p + factor, p + 2 * factor, ...`. The response
+/// is zero-padded so that every component has the same (nonzero) length.
+fn polyphase_components(taps: &[Real], factor: usize) -> impl Iterator<Item = Vec<Real>> + '_ {
+    let len = taps.len().div_ceil(factor).max(1);
+    (0..factor).map(move |phase| {
+        (0..len)
+            .map(|j| taps.get(j * factor + phase).copied().unwrap_or(0.0))
+            .collect()
+    })
+}
+
+/// Interpolate by an integer factor.
+///
+/// This is equivalent to inserting `factor - 1` zeros after each input sample
+/// and filtering the result (with a gain of `factor` to compensate for the
+/// zeros), but is implemented as a polyphase filter bank which skips the
+/// multiplications by zero.
+pub struct Upsample<T = Real> {
+    branches: Box<[Fir<T>]>,
+}
+
+impl<T: FirSample> Upsample<T> {
+    pub fn new(factor: usize, filter: Fir<T>) -> Self {
+        let gain = factor as Real;
+        let branches = polyphase_components(filter.taps(), factor)
+            .map(|taps| Fir::new(taps.into_iter().map(|tap| tap * gain).collect::<Vec<_>>()))
+            .collect();
+        Self { branches }
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len() * self.branches.len(), output.len());
+        for (out, &inp) in output.chunks_mut(self.branches.len()).zip(input) {
+            for (slot, branch) in out.iter_mut().zip(&mut self.branches[..]) {
+                *slot = branch.process_sample(inp);
+            }
+        }
+    }
+}
+
+/// Decimate by an integer factor.
+///
+/// This is equivalent to filtering the input and keeping every `factor`th
+/// output sample, but is implemented as a polyphase filter bank which only
+/// computes the outputs that are kept.
+pub struct Downsample<T = Real> {
+    branches: Box<[Fir<T>]>,
+}
+
+impl<T: FirSample> Downsample<T> {
+    pub fn new(factor: usize, filter: Fir<T>) -> Self {
+        let branches = polyphase_components(filter.taps(), factor)
+            .map(Fir::new)
+            .collect();
+        Self { branches }
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len() * self.branches.len());
+        for (inp, out) in input.chunks(self.branches.len()).zip(output) {
+            // Branch `p` sees the input delayed by `p` samples, so the newest
+            // sample of each chunk goes to the first branch.
+            *out = inp
+                .iter()
+                .rev()
+                .zip(&mut self.branches[..])
+                .map(|(&sample, branch)| branch.process_sample(sample))
+                .sum();
+        }
+    }
+}
+
+/// Resample by a rational factor, `interpolation / decimation`.
+///
+/// This is equivalent to an [`Upsample`] with the given filter, which runs at
+/// `interpolation` times the input rate, keeping only every `decimation`th
+/// output (a [`Downsample`] with a single unit tap). Only the outputs that are
+/// kept are computed, each using a single polyphase branch. The filter must
+/// therefore band-limit the signal for both rate changes. The factors should
+/// be in lowest terms.
+///
+/// Unlike [`Upsample`] and [`Downsample`], input of any length is accepted,
+/// and the outputs are appended to a `Vec` as they become available.
+pub struct Rational<T = Real> {
+    branches: Box<[Box<[Real]>]>,
+    decimation: usize,
+    history: DelayLine<T>,
+    phase: usize,
+}
+
+impl<T: FirSample> Rational<T> {
+    pub fn new(interpolation: usize, decimation: usize, filter: Fir<T>) -> Self {
+        assert!(interpolation > 0 && decimation > 0);
+        let gain = interpolation as Real;
+        let branches: Box<[Box<[Real]>]> = polyphase_components(filter.taps(), interpolation)
+            .map(|taps| taps.into_iter().map(|tap| tap * gain).collect())
+            .collect();
+        Self {
+            history: DelayLine::new(branches[0].len()),
+            branches,
+            decimation,
+            phase: 0,
+        }
+    }
+
+    pub fn interpolation(&self) -> usize {
+        self.branches.len()
+    }
+
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    pub fn process(&mut self, input: &[T], output: &mut Vec<T>) {
+        for &sample in input {
+            self.history.push(sample);
+
+            // `phase` is the position of the next output, in samples at the
+            // interpolated rate, after this input sample.
+            while self.phase < self.branches.len() {
+                output.push(T::dot(&self.branches[self.phase], self.history.window()));
+                self.phase += self.decimation;
+            }
+            self.phase -= self.branches.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iq::IQ;
+
+    /// An asymmetric impulse response, so that reversing it would show.
+    const TAPS: [Real; 11] = [
+        1.0, 0.8, -0.5, 0.3, 0.25, -0.2, 0.1, 0.07, -0.05, 0.02, 0.01,
+    ];
+
+    fn signal(len: usize) -> Vec<Real> {
+        (0..len)
+            .map(|n| ((n * 7919) % 31) as Real / 31.0 - 0.5)
+            .collect()
+    }
+
+    /// Direct convolution, `y[n] = sum(h[k] * x[n - k])`.
+    fn convolve(taps: &[Real], input: &[Real]) -> Vec<Real> {
+        (0..input.len())
+            .map(|n| {
+                taps.iter()
+                    .take(n + 1)
+                    .enumerate()
+                    .map(|(k, &tap)| tap * input[n - k])
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Insert `factor - 1` zeros after each sample.
+    fn zero_stuff(input: &[Real], factor: usize) -> Vec<Real> {
+        input
+            .iter()
+            .flat_map(|&sample| {
+                std::iter::once(sample * factor as Real).chain(std::iter::repeat_n(0.0, factor - 1))
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[Real], expected: &[Real]) {
+        assert_eq!(actual.len(), expected.len());
+        for (n, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-5, "sample {n}: {a} != {e}");
+        }
+    }
+
+    #[test]
+    fn fir_impulse_response_is_taps() {
+        let mut fir: Fir = Fir::new(TAPS);
+        let mut buffer = [0.0; 16];
+        buffer[0] = 1.0;
+        fir.process_inplace(&mut buffer);
+        assert_eq!(&buffer[..TAPS.len()], &TAPS);
+        assert!(buffer[TAPS.len()..].iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn upsample_matches_zero_stuffing() {
+        let input = signal(40);
+        for factor in [1, 2, 3, 4, 5] {
+            let mut upsample = Upsample::new(factor, Fir::new(TAPS));
+            let mut output = vec![0.0; input.len() * factor];
+            // Split the input to check that state carries over.
+            let (first, second) = input.split_at(17);
+            let (out_first, out_second) = output.split_at_mut(17 * factor);
+            upsample.process(first, out_first);
+            upsample.process(second, out_second);
+
+            let expected = convolve(&TAPS, &zero_stuff(&input, factor));
+            assert_close(&output, &expected);
+        }
+    }
+
+    #[test]
+    fn downsample_matches_filter_and_pick() {
+        let input = signal(60);
+        for factor in [1, 2, 3, 4, 5] {
+            let mut downsample = Downsample::new(factor, Fir::new(TAPS));
+            let mut output = vec![0.0; input.len() / factor];
+            let split = 6 * factor;
+            let (out_first, out_second) = output.split_at_mut(6);
+            downsample.process(&input[..split], out_first);
+            downsample.process(&input[split..], out_second);
+
+            // Keep the output for the last input sample of each block, as
+            // `Fir::decimate` does.
+            let expected: Vec<Real> = convolve(&TAPS, &input)
+                .into_iter()
+                .skip(factor - 1)
+                .step_by(factor)
+                .collect();
+            assert_close(&output, &expected);
+        }
+    }
+
+    /// Run `Rational` over `input`, fed in chunks of varying size, and check
+    /// it against an `Upsample` with the same filter followed by a
+    /// `Downsample` that only picks samples.
+    fn check_rational<T: FirSample + std::fmt::Debug>(input: &[T]) {
+        for (interpolation, decimation) in [(1, 1), (2, 1), (1, 3), (3, 2), (2, 5), (5, 3)] {
+            let mut rational = Rational::new(interpolation, decimation, Fir::new(TAPS));
+            let mut output = Vec::new();
+            let mut rest = input;
+            for chunk in [1, 4, 2, 7, 3, 11].into_iter().cycle() {
+                let (chunk, tail) = rest.split_at(chunk.min(rest.len()));
+                rational.process(chunk, &mut output);
+                rest = tail;
+                if rest.is_empty() {
+                    break;
+                }
+            }
+            assert_eq!(output.len(), input.len() * interpolation / decimation);
+
+            // `Rational` keeps the first of every `decimation` interpolated
+            // samples, and `Downsample` the last, so delay the interpolated
+            // samples by `decimation - 1` to line them up.
+            let mut upsampled = vec![T::ZERO; decimation - 1 + input.len() * interpolation];
+            Upsample::new(interpolation, Fir::new(TAPS))
+                .process(input, &mut upsampled[decimation - 1..]);
+            let mut expected = vec![T::ZERO; upsampled.len() / decimation];
+            Downsample::new(decimation, Fir::new([1.0]))
+                .process(&upsampled[..expected.len() * decimation], &mut expected);
+
+            assert_eq!(output.len(), expected.len());
+            for (n, (&a, &e)) in output.iter().zip(&expected).enumerate() {
+                assert!(
+                    (a - e).magnitude() < 1e-5,
+                    "{interpolation}/{decimation}: sample {n}: {a:?} != {e:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rational_matches_upsample_then_downsample() {
+        // A multiple of every decimation factor, so the output length is exact.
+        let input = signal(90);
+        check_rational(&input);
+        let complex: Vec<IQ> = input
+            .iter()
+            .zip(input.iter().rev())
+            .map(|(&i, &q)| IQ::new(i, q))
+            .collect();
+        check_rational(&complex);
+    }
+}
@@ -16,4 +16,27 @@ pub trait Sample:
     fn magnitude(&self) -> Real;
 
     fn magnitude_squared(&self) -> Real;
+
+    /// `sum(samples[k] * weights[k])` over the shorter of the two slices.
+    ///
+    /// This is the inner loop of a [`Fir`](crate::filter::Fir) with real taps.
+    /// The default is a plain scalar loop; [`Real`] and
+    /// [`IQ`](crate::iq::IQ) override it with SIMD kernels.
+    fn weighted_sum(weights: &[Real], samples: &[Self]) -> Self {
+        scalar_dot(weights, samples)
+    }
+}
+
+/// `sum(samples[k] * taps[k])` over the shorter of the two slices, as a plain
+/// scalar loop.
+pub(crate) fn scalar_dot<T, C>(taps: &[C], samples: &[T]) -> T
+where
+    T: Sample + ops::Mul<C, Output = T>,
+    C: Copy,
+{
+    samples
+        .iter()
+        .zip(taps)
+        .map(|(&sample, &tap)| sample * tap)
+        .sum()
 }
@@ -0,0 +1,328 @@
+//! Minimal RGB raster images, with PNG and PGM output and a tiny font for
+//! labels.
+//!
+//! This is just enough to render plots without pulling in an imaging crate.
+//! PNG data is written with uncompressed ("stored") deflate blocks, so files
+//! are about as large as the raw pixels, but any PNG reader can open them.
+
+use std::io::{self, Write};
+
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+
+/// Width of a glyph in the built-in font, in pixels at scale 1.
+pub const GLYPH_WIDTH: usize = 3;
+
+/// Height of a glyph in the built-in font, in pixels at scale 1.
+pub const GLYPH_HEIGHT: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Pixels in row-major order, starting from the top left.
+    pub fn pixels(&self) -> &[Rgb] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        assert!(x < self.width && y < self.height);
+        self.pixels[y * self.width + x]
+    }
+
+    /// Set a pixel. Pixels outside the image are ignored, so shapes and text
+    /// are clipped at the edges.
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Width of `text` drawn at `scale`, in pixels.
+    pub fn text_width(text: &str, scale: usize) -> usize {
+        let chars = text.chars().count();
+        (chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+    }
+
+    /// Draw `text` with its top left corner at `(x, y)`, with each font pixel
+    /// drawn as a `scale` by `scale` square.
+    ///
+    /// The font only covers what plot labels need: digits, `+ - . :` and the
+    /// letters of common units (`dB Hz k M s m u`). Other characters are
+    /// drawn as blanks.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, scale: usize, color: Rgb) {
+        for (index, c) in text.chars().enumerate() {
+            let left = x + index * (GLYPH_WIDTH + 1) * scale;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill_rect(left + column * scale, y + row * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write the image as an 8-bit RGB PNG.
+    pub fn write_png(&self, mut writer: impl Write) -> io::Result<()> {
+        let width = u32::try_from(self.width).map_err(|_| too_large())?;
+        let height = u32::try_from(self.height).map_err(|_| too_large())?;
+
+        // Each scanline is prefixed with its filter type, 0 for none.
+        let mut raw = Vec::with_capacity(self.height * (3 * self.width + 1));
+        for row in self
+            .pixels
+            .chunks_exact(self.width.max(1))
+            .take(self.height)
+        {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(width.to_be_bytes());
+        header.extend(height.to_be_bytes());
+        // Bit depth 8, color type 2 (RGB), default compression, filtering and
+        // no interlacing.
+        header.extend([8, 2, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(&mut writer, b"IEND", &[])
+    }
+
+    /// Write the image as a binary (P5) PGM, converting each pixel to its
+    /// luma.
+    pub fn write_pgm(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let luma: Vec<u8> = self
+            .pixels
+            .iter()
+            .map(|&[r, g, b]| {
+                ((299 * r as u32 + 587 * g as u32 + 114 * b as u32 + 500) / 1000) as u8
+            })
+            .collect();
+        writer.write_all(&luma)
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "image too large for PNG")
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len()).map_err(|_| too_large())?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(crc32(!0, kind), data);
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+/// Wrap `data` in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+
+    let num_blocks = data.len().div_ceil(MAX_BLOCK).max(1);
+    let mut stream = Vec::with_capacity(data.len() + 5 * num_blocks + 6);
+    // Deflate with a 32K window and no preset dictionary; the check bits make
+    // the header a multiple of 31.
+    stream.extend([0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(last as u8);
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(block);
+    }
+
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// Update a running CRC-32, without the initial and final inversion.
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Rows of a 3x5 glyph, top to bottom, with the leftmost pixel in bit 2.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'd' => [0b001, 0b001, 0b111, 0b101, 0b111],
+        'k' => [0b100, 0b101, 0b110, 0b101, 0b101],
+        'm' => [0b000, 0b000, 0b111, 0b111, 0b101],
+        's' => [0b000, 0b011, 0b100, 0b001, 0b110],
+        'u' => [0b000, 0b000, 0b101, 0b101, 0b111],
+        'z' => [0b000, 0b111, 0b011, 0b110, 0b111],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_zlib() {
+        assert_eq!(!crc32(!0, b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"123456789"), 0x091e01de);
+        assert_eq!(adler32(b""), 1);
+        // Long enough to need the periodic reduction of the sums.
+        assert_eq!(adler32(&[0xff; 100000]), 0x149a302c);
+        assert_eq!(!crc32(!0, &[0xff; 100000]), 0x68c6cec4);
+    }
+
+    #[test]
+    fn png_snapshot() {
+        let mut image = Image::new(2, 2, WHITE);
+        image.set(0, 0, [255, 0, 0]);
+        image.set(1, 0, [0, 255, 0]);
+        image.set(0, 1, [0, 0, 255]);
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        // As written by Python's zlib and struct modules.
+        #[rustfmt::skip]
+        let expected = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a,
+            // IHDR
+            0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x02, 0x08, 0x02, 0x00, 0x00, 0x00, 0xfd, 0xd4, 0x9a,
+            0x73,
+            // IDAT
+            0x00, 0x00, 0x00, 0x19, 0x49, 0x44, 0x41, 0x54, 0x78, 0x01, 0x01, 0x0e,
+            0x00, 0xf1, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00,
+            0x00, 0xff, 0xff, 0xff, 0xff, 0x1f, 0xee, 0x05, 0xfb, 0xde, 0xdd, 0xec,
+            0x2b,
+            // IEND
+            0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        assert_eq!(png, expected);
+    }
+
+    #[test]
+    fn zlib_stored_splits_long_data() {
+        let data: Vec<u8> = (0..150000u32).map(|n| (n * 7 % 251) as u8).collect();
+        let stream = zlib_stored(&data);
+        assert_eq!(stream[..2], [0x78, 0x01]);
+
+        // Blocks of at most 65535 bytes, each with its length and the
+        // complement of its length, with the last one marked.
+        let mut position = 2;
+        let mut inflated: Vec<u8> = Vec::new();
+        let mut block_lens = Vec::new();
+        loop {
+            let last = stream[position];
+            let len = u16::from_le_bytes([stream[position + 1], stream[position + 2]]);
+            let complement = u16::from_le_bytes([stream[position + 3], stream[position + 4]]);
+            assert_eq!(complement, !len);
+            position += 5;
+            inflated.extend(&stream[position..position + len as usize]);
+            position += len as usize;
+            block_lens.push(len);
+            if last == 1 {
+                break;
+            }
+            assert_eq!(last, 0);
+        }
+        assert_eq!(block_lens, [65535, 65535, 18930]);
+        assert_eq!(inflated, data);
+        assert_eq!(stream[position..], adler32(&data).to_be_bytes());
+
+        // Empty data is a single empty final block.
+        assert_eq!(
+            zlib_stored(&[]),
+            [0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! Power spectrum estimation by Welch's method of averaged periodograms.
+
+use crate::{
+    fft::{Fft, RealFft},
+    filter::Window,
+    iq::IQ,
+    math::Real,
+    sample::Sample,
+};
+
+pub mod image;
+pub mod waterfall;
+
+/// Sample types that a spectrum can be estimated for.
+///
+/// Real signals have a conjugate-symmetric spectrum, so only the one-sided
+/// spectrum from zero to Nyquist is estimated, with the power of the negative
+/// frequencies folded into the positive ones. Complex signals get the full
+/// two-sided spectrum, ordered from `-sample_rate / 2` up to just below
+/// `sample_rate / 2` with DC in the middle, as it would be displayed.
+pub trait SpectrumSample: Sample {
+    type Plan: Clone;
+
+    /// Power of a full-scale signal, 0 dBFS: a sinusoid with an amplitude of
+    /// 1 for real signals, or a complex exponential with a magnitude of 1.
+    const FULL_SCALE_POWER: Real;
+
+    fn plan(size: usize) -> Self::Plan;
+
+    fn num_bins(size: usize) -> usize;
+
+    /// Frequency of bin `index` in a transform of `size` samples.
+    fn bin_freq(index: usize, size: usize, sample_rate: Real) -> Real;
+
+    /// Transform `input` multiplied by `window`, writing the squared
+    /// magnitude of each bin to `output`.
+    ///
+    /// `windowed` (as long as the input) and `spectrum` (as long as the
+    /// output) are scratch space, so that no memory is allocated per segment.
+    fn periodogram(
+        plan: &Self::Plan,
+        input: &[Self],
+        window: &[Real],
+        windowed: &mut [Self],
+        spectrum: &mut [IQ],
+        output: &mut [Real],
+    );
+}
+
+impl SpectrumSample for Real {
+    type Plan = RealFft;
+
+    const FULL_SCALE_POWER: Real = 0.5;
+
+    fn plan(size: usize) -> Self::Plan {
+        RealFft::new(size)
+    }
+
+    fn num_bins(size: usize) -> usize {
+        size / 2 + 1
+    }
+
+    fn bin_freq(index: usize, size: usize, sample_rate: Real) -> Real {
+        index as Real * sample_rate / size as Real
+    }
+
+    fn periodogram(
+        plan: &Self::Plan,
+        input: &[Self],
+        window: &[Real],
+        windowed: &mut [Self],
+        spectrum: &mut [IQ],
+        output: &mut [Real],
+    ) {
+        for ((slot, &x), &w) in windowed.iter_mut().zip(input).zip(window) {
+            *slot = x * w;
+        }
+        plan.forward(windowed, spectrum);
+
+        let size = plan.size();
+        for (k, (slot, value)) in output.iter_mut().zip(&*spectrum).enumerate() {
+            // Every bin but DC and Nyquist also stands for its negative
+            // frequency.
+            let folded = k != 0 && 2 * k != size;
+            *slot = value.magnitude_squared() * if folded { 2.0 } else { 1.0 };
+        }
+    }
+}
+
+impl SpectrumSample for IQ {
+    type Plan = Fft;
+
+    const FULL_SCALE_POWER: Real = 1.0;
+
+    fn plan(size: usize) -> Self::Plan {
+        Fft::new(size)
+    }
+
+    fn num_bins(size: usize) -> usize {
+        size
+    }
+
+    fn bin_freq(index: usize, size: usize, sample_rate: Real) -> Real {
+        (index as Real - (size / 2) as Real) * sample_rate / size as Real
+    }
+
+    fn periodogram(
+        plan: &Self::Plan,
+        input: &[Self],
+        window: &[Real],
+        _windowed: &mut [Self],
+        spectrum: &mut [IQ],
+        output: &mut [Real],
+    ) {
+        // The transform is in place, so the spectrum is windowed directly.
+        for ((slot, &x), &w) in spectrum.iter_mut().zip(input).zip(window) {
+            *slot = x * w;
+        }
+        plan.forward(spectrum);
+
+        // Rotate the negative frequencies to the front.
+        let size = spectrum.len();
+        for (i, slot) in output.iter_mut().enumerate() {
+            *slot = spectrum[(i + size - size / 2) % size].magnitude_squared();
+        }
+    }
+}
+
+/// How successive periodograms are combined into an estimate.
+#[derive(Debug, Clone, Copy)]
+pub enum Averaging {
+    /// Mean of every `count` periodograms, giving a new, independent estimate
+    /// after each `count` segments. The variance of the estimate goes down by
+    /// about a factor of `count`, or more with overlapping segments.
+    Linear { count: usize },
+    /// Exponentially weighted mean, updated after every segment, with older
+    /// segments decaying as `exp(-t / time_constant)` (in seconds).
+    Exponential { time_constant: Real },
+    /// Maximum of each bin over all segments since the last reset.
+    PeakHold,
+}
+
+/// Design parameters for a [`SpectrumAnalyzer`].
+#[derive(Debug, Clone, Copy)]
+pub struct Welch {
+    /// Number of samples per segment, and of the FFT.
+    pub size: usize,
+    pub window: Window,
+    /// Fraction of each segment shared with the next one, from 0 to just
+    /// below 1. With tapered windows, 0.5 or more lets the samples near the
+    /// segment edges contribute as much as the ones in the middle.
+    pub overlap: Real,
+    pub averaging: Averaging,
+    pub sample_rate: Real,
+}
+
+impl Welch {
+    /// Number of samples between the starts of consecutive segments.
+    pub fn hop(&self) -> usize {
+        assert!((0.0..1.0).contains(&self.overlap));
+        ((self.size as Real * (1.0 - self.overlap)).round() as usize).max(1)
+    }
+
+    /// Frequency resolution, the spacing between bins, in Hz.
+    pub fn resolution(&self) -> Real {
+        self.sample_rate / self.size as Real
+    }
+
+    /// Window coefficients for one segment; see [`Window::generate`].
+    pub fn window_taps(&self) -> Box<[Real]> {
+        self.window.generate(self.size)
+    }
+
+    pub fn build<T: SpectrumSample>(&self) -> SpectrumAnalyzer<T> {
+        SpectrumAnalyzer::new(*self)
+    }
+}
+
+/// Streaming spectrum analyzer.
+///
+/// Input is split into windowed segments of [`Welch::size`] samples,
+/// overlapping by [`Welch::overlap`], and the periodograms of the segments are
+/// averaged. Samples are buffered between calls, so the input can be fed in
+/// blocks of any length.
+///
+/// The estimate is scaled as a power spectrum: a tone that falls exactly on a
+/// bin reads its mean power in that bin, independent of the window, which is
+/// the natural scaling for [`dbfs`](Self::dbfs). For noise, which spreads
+/// over many bins, use [`density`](Self::density).
+#[derive(Clone)]
+pub struct SpectrumAnalyzer<T: SpectrumSample = Real> {
+    design: Welch,
+    plan: T::Plan,
+    window: Box<[Real]>,
+    /// Square of the sum of the window, which scales the periodograms.
+    gain_squared: Real,
+    /// Sum of the squared window.
+    energy: Real,
+    hop: usize,
+    /// Exponential averaging weight of the previous estimate.
+    decay: Real,
+    input: Vec<T>,
+    windowed: Box<[T]>,
+    spectrum: Box<[IQ]>,
+    periodogram: Box<[Real]>,
+    accumulator: Box<[Real]>,
+    segments: usize,
+    estimate: Box<[Real]>,
+}
+
+impl<T: SpectrumSample> SpectrumAnalyzer<T> {
+    pub fn new(design: Welch) -> Self {
+        assert!(design.size > 0);
+        let window = design.window_taps();
+        let hop = design.hop();
+        let decay = match design.averaging {
+            Averaging::Linear { count } => {
+                assert!(count > 0);
+                0.0
+            }
+            Averaging::Exponential { time_constant } => {
+                assert!(time_constant > 0.0);
+                (-(hop as Real) / (time_constant * design.sample_rate)).exp()
+            }
+            Averaging::PeakHold => 0.0,
+        };
+        let num_bins = T::num_bins(design.size);
+        Self {
+            plan: T::plan(design.size),
+            gain_squared: window.iter().sum::<Real>().powi(2),
+            energy: window.iter().map(|w| w * w).sum(),
+            window,
+            hop,
+            decay,
+            input: Vec::with_capacity(2 * design.size),
+            windowed: vec![T::ZERO; design.size].into_boxed_slice(),
+            spectrum: vec![IQ::ZERO; num_bins].into_boxed_slice(),
+            periodogram: vec![0.0; num_bins].into_boxed_slice(),
+            accumulator: vec![0.0; num_bins].into_boxed_slice(),
+            segments: 0,
+            estimate: vec![0.0; num_bins].into_boxed_slice(),
+            design,
+        }
+    }
+
+    pub fn design(&self) -> &Welch {
+        &self.design
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.estimate.len()
+    }
+
+    /// Center frequency of each bin, in Hz.
+    pub fn freqs(&self) -> impl Iterator<Item = Real> + '_ {
+        let size = self.design.size;
+        let sample_rate = self.design.sample_rate;
+        (0..self.num_bins()).map(move |index| T::bin_freq(index, size, sample_rate))
+    }
+
+    /// Add samples, returning `true` if the estimate was updated.
+    pub fn process(&mut self, input: &[T]) -> bool {
+        let size = self.design.size;
+        let mut updated = false;
+        for chunk in input.chunks(size) {
+            self.input.extend_from_slice(chunk);
+            while self.input.len() >= size {
+                T::periodogram(
+                    &self.plan,
+                    &self.input[..size],
+                    &self.window,
+                    &mut self.windowed,
+                    &mut self.spectrum,
+                    &mut self.periodogram,
+                );
+                let scale = 1.0 / self.gain_squared;
+                for power in &mut self.periodogram[..] {
+                    *power *= scale;
+                }
+                updated |= self.accumulate();
+                self.input.drain(..self.hop);
+            }
+        }
+        updated
+    }
+
+    /// Fold the latest periodogram into the estimate, returning `true` if a
+    /// new estimate is available.
+    fn accumulate(&mut self) -> bool {
+        let first = self.segments == 0;
+        self.segments += 1;
+        let periodogram = self.periodogram.iter();
+        match self.design.averaging {
+            Averaging::Linear { count } => {
+                for (sum, &power) in self.accumulator.iter_mut().zip(periodogram) {
+                    *sum += power;
+                }
+                if self.segments < count {
+                    return false;
+                }
+                let scale = 1.0 / count as Real;
+                for (estimate, sum) in self.estimate.iter_mut().zip(&mut self.accumulator[..]) {
+                    *estimate = *sum * scale;
+                    *sum = 0.0;
+                }
+                self.segments = 0;
+            }
+            Averaging::Exponential { .. } => {
+                for (estimate, &power) in self.estimate.iter_mut().zip(periodogram) {
+                    *estimate = if first {
+                        power
+                    } else {
+                        *estimate * self.decay + power * (1.0 - self.decay)
+                    };
+                }
+            }
+            Averaging::PeakHold => {
+                for (estimate, &power) in self.estimate.iter_mut().zip(periodogram) {
+                    *estimate = if first { power } else { estimate.max(power) };
+                }
+            }
+        }
+        true
+    }
+
+    /// Discard buffered input and start averaging from scratch.
+    ///
+    /// The last estimate remains available until it is replaced.
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.accumulator.fill(0.0);
+        self.segments = 0;
+    }
+
+    /// The latest estimate, as the power in each bin.
+    pub fn power(&self) -> &[Real] {
+        &self.estimate
+    }
+
+    /// The latest estimate in dB relative to a full-scale signal (see
+    /// [`SpectrumSample::FULL_SCALE_POWER`]).
+    pub fn dbfs(&self) -> impl Iterator<Item = Real> + '_ {
+        self.estimate
+            .iter()
+            .map(|&power| 10.0 * (power / T::FULL_SCALE_POWER).log10())
+    }
+
+    /// The latest estimate as a power spectral density, in power per Hz.
+    ///
+    /// This divides the power in each bin by the window's equivalent noise
+    /// bandwidth (see [`Window::enbw`]), so that white noise reads the same
+    /// regardless of the window and segment size.
+    pub fn density(&self) -> impl Iterator<Item = Real> + '_ {
+        let bandwidth = self.design.sample_rate * self.energy / self.gain_squared;
+        self.estimate.iter().map(move |&power| power / bandwidth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::TAU;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const WINDOWS: [Window; 4] = [
+        Window::Rectangular,
+        Window::HANN,
+        Window::BLACKMAN_HARRIS,
+        Window::FLAT_TOP,
+    ];
+
+    fn design(window: Window, averaging: Averaging) -> Welch {
+        Welch {
+            size: 256,
+            window,
+            overlap: 0.5,
+            averaging,
+            sample_rate: 8000.0,
+        }
+    }
+
+    /// Samples of a tone at `bin` cycles per 256 samples, with the phase
+    /// reduced exactly so that it doesn't lose precision.
+    fn tone_phase(bin: usize, n: usize) -> Real {
+        TAU * ((bin * n) % 256) as Real / 256.0
+    }
+
+    #[test]
+    fn full_scale_tone_reads_0_dbfs() {
+        let averaging = Averaging::Linear { count: 3 };
+        for window in WINDOWS {
+            let real: Vec<Real> = (0..2048).map(|n| tone_phase(40, n).sin()).collect();
+            let mut analyzer = design(window, averaging).build();
+            assert!(analyzer.process(&real));
+            let level = analyzer.dbfs().nth(40).unwrap();
+            assert!(level.abs() < 1e-3, "{window:?}: {level}");
+
+            // -40 bins, which is bin 88 counting from -128.
+            let complex: Vec<IQ> = (0..2048)
+                .map(|n| IQ::new_polar(-tone_phase(40, n), 1.0))
+                .collect();
+            let mut analyzer = design(window, averaging).build();
+            assert!(analyzer.process(&complex));
+            assert_eq!(analyzer.freqs().nth(88), Some(-1250.0));
+            let level = analyzer.dbfs().nth(88).unwrap();
+            assert!(level.abs() < 1e-3, "{window:?}: {level}");
+        }
+    }
+
+    #[test]
+    fn noise_density_is_independent_of_window() {
+        // Uniform noise with a variance of 1 / 3 in each component, enough
+        // for 1000 segments.
+        let mut rng = StdRng::seed_from_u64(19);
+        let len = 1000 * 128 + 128;
+        let real: Vec<Real> = (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let complex: Vec<IQ> = (0..len)
+            .map(|_| IQ::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect();
+        let averaging = Averaging::Linear { count: 1000 };
+        let mean = |density: &[Real]| density.iter().sum::<Real>() / density.len() as Real;
+
+        for window in WINDOWS {
+            let mut analyzer = design(window, averaging).build();
+            assert!(analyzer.process(&real));
+            // The one-sided density is twice the variance over the sample
+            // rate, leaving out DC and Nyquist.
+            let density: Vec<Real> = analyzer.density().collect();
+            let level = mean(&density[1..128]) / (2.0 / 3.0 / 8000.0);
+            assert!((level - 1.0).abs() < 0.03, "{window:?}: {level}");
+
+            let mut analyzer = design(window, averaging).build();
+            assert!(analyzer.process(&complex));
+            let density: Vec<Real> = analyzer.density().collect();
+            let level = mean(&density) / (2.0 / 3.0 / 8000.0);
+            assert!((level - 1.0).abs() < 0.03, "{window:?}: {level}");
+        }
+    }
+
+    #[test]
+    fn update_cadence() {
+        let tone: Vec<Real> = (0..128 * 10).map(|n| tone_phase(40, n).sin()).collect();
+        for (averaging, updates) in [
+            (Averaging::Linear { count: 3 }, &[4, 7, 10][..]),
+            (
+                Averaging::Exponential { time_constant: 0.1 },
+                &[2, 3, 4, 5, 6, 7, 8, 9, 10],
+            ),
+            (Averaging::PeakHold, &[2, 3, 4, 5, 6, 7, 8, 9, 10]),
+        ] {
+            // One hop at a time; the first segment completes after two.
+            let mut analyzer = design(Window::HANN, averaging).build();
+            let updated: Vec<usize> = (1..=10)
+                .filter(|&hops| analyzer.process(&tone[128 * (hops - 1)..128 * hops]))
+                .collect();
+            assert_eq!(updated, updates, "{averaging:?}");
+        }
+    }
+
+    #[test]
+    fn exponential_decay_and_peak_hold() {
+        // A tone followed by silence.
+        let mut input: Vec<Real> = (0..2048).map(|n| tone_phase(40, n).sin()).collect();
+        input.resize(4096, 0.0);
+
+        // A time constant of one hop, so each silent segment scales the
+        // estimate by `1 / e`.
+        let exponential = Averaging::Exponential {
+            time_constant: 128.0 / 8000.0,
+        };
+        let mut analyzer = design(Window::HANN, exponential).build();
+        let mut levels = Vec::new();
+        for hop in input.chunks(128) {
+            if analyzer.process(hop) {
+                levels.push(analyzer.power()[40]);
+            }
+        }
+        assert!((levels[10] / 0.5 - 1.0).abs() < 1e-4, "{}", levels[10]);
+        // From the first segment that is entirely silent.
+        for pair in levels[16..].windows(2) {
+            assert!((pair[1] / pair[0] - (-1.0 as Real).exp()).abs() < 1e-4);
+        }
+
+        let mut analyzer = design(Window::HANN, Averaging::PeakHold).build();
+        analyzer.process(&input);
+        assert!((analyzer.power()[40] / 0.5 - 1.0).abs() < 1e-4);
+        // Until a reset, after which only silence is seen.
+        analyzer.reset();
+        analyzer.process(&input[2048..]);
+        assert!(analyzer.power()[40] < 1e-12);
+    }
+
+    #[test]
+    fn chunk_size_does_not_matter() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let input: Vec<IQ> = (0..5000)
+            .map(|_| IQ::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+            .collect();
+        for averaging in [
+            Averaging::Linear { count: 4 },
+            Averaging::Exponential {
+                time_constant: 0.05,
+            },
+            Averaging::PeakHold,
+        ] {
+            let mut whole = design(Window::HANN, averaging).build();
+            whole.process(&input);
+            for chunk_size in [1, 7, 128, 300, 1000] {
+                let mut chunked = design(Window::HANN, averaging).build();
+                for chunk in input.chunks(chunk_size) {
+                    chunked.process(chunk);
+                }
+                assert_eq!(
+                    chunked.power(),
+                    whole.power(),
+                    "{averaging:?}, {chunk_size}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Waterfall (spectrogram) rendering.
+
+use crate::{
+    math::Real,
+    spectrum::{
+        image::{Image, Rgb, BLACK, GLYPH_HEIGHT, WHITE},
+        Averaging, SpectrumAnalyzer, SpectrumSample, Welch,
+    },
+};
+
+/// Size of the font used for axis labels, in pixels per font pixel.
+const TEXT_SCALE: usize = 2;
+
+/// Length of the axis tick marks, in pixels.
+const TICK_LENGTH: usize = 4;
+
+/// Maps values between 0 and 1 to colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Grayscale,
+    /// Perceptually uniform blue-green-yellow, also readable in grayscale and
+    /// by most color-blind viewers.
+    Viridis,
+    /// Perceptually uniform black-red-yellow-white.
+    Inferno,
+    /// Rainbow from dark blue to dark red, as on many receiver displays.
+    Jet,
+}
+
+impl Colormap {
+    /// Color for `value`, which is clamped to the range 0 to 1.
+    pub fn color(&self, value: Real) -> Rgb {
+        let stops: &[Rgb] = match self {
+            Self::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+            Self::Viridis => &[
+                [68, 1, 84],
+                [72, 40, 120],
+                [62, 74, 137],
+                [49, 104, 142],
+                [38, 130, 142],
+                [31, 158, 137],
+                [53, 183, 121],
+                [110, 206, 88],
+                [181, 222, 43],
+                [253, 231, 37],
+            ],
+            Self::Inferno => &[
+                [0, 0, 4],
+                [27, 12, 65],
+                [74, 12, 107],
+                [120, 28, 109],
+                [165, 44, 96],
+                [207, 68, 70],
+                [237, 105, 37],
+                [251, 155, 6],
+                [247, 209, 61],
+                [252, 255, 164],
+            ],
+            Self::Jet => &[
+                [0, 0, 128],
+                [0, 0, 255],
+                [0, 128, 255],
+                [0, 255, 255],
+                [128, 255, 128],
+                [255, 255, 0],
+                [255, 128, 0],
+                [255, 0, 0],
+                [128, 0, 0],
+            ],
+        };
+
+        // NaN goes to the bottom of the scale, like -inf.
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let position = value * (stops.len() - 1) as Real;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as Real;
+        let (low, high) = (stops[index], stops[index + 1]);
+        std::array::from_fn(|channel| {
+            let low = low[channel] as Real;
+            let high = high[channel] as Real;
+            (low + (high - low) * fraction).round() as u8
+        })
+    }
+}
+
+/// How to turn a [`Waterfall`] into an image.
+#[derive(Debug, Clone, Copy)]
+pub struct Render {
+    pub colormap: Colormap,
+    /// Level at the bottom of the colormap, in dBFS.
+    pub min_db: Real,
+    /// Level at the top of the colormap, in dBFS.
+    pub max_db: Real,
+    /// Draw frequency and time axes with labels below and left of the
+    /// spectrogram.
+    pub axes: bool,
+}
+
+/// Spectrogram of a stream, one row of spectrum estimates at a time.
+///
+/// Every estimate from a [`SpectrumAnalyzer`] becomes a row, in dBFS. With
+/// [`Averaging::Linear`], `count` segments are averaged per row; with the other
+/// modes, each segment gives a row of the running estimate.
+///
+/// All rows are kept until [`clear`](Self::clear) is called, so memory grows
+/// with the length of the stream. For long or endless streams, render and
+/// clear the waterfall periodically.
+#[derive(Clone)]
+pub struct Waterfall<T: SpectrumSample = Real> {
+    analyzer: SpectrumAnalyzer<T>,
+    rows: Vec<Box<[Real]>>,
+}
+
+impl<T: SpectrumSample> Waterfall<T> {
+    pub fn new(design: Welch) -> Self {
+        Self {
+            analyzer: SpectrumAnalyzer::new(design),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn design(&self) -> &Welch {
+        self.analyzer.design()
+    }
+
+    /// Rows so far, oldest first, each with one level per bin in dBFS.
+    pub fn rows(&self) -> &[Box<[Real]>] {
+        &self.rows
+    }
+
+    /// Time between rows, in seconds.
+    pub fn row_interval(&self) -> Real {
+        let design = self.design();
+        let segments = match design.averaging {
+            Averaging::Linear { count } => count,
+            Averaging::Exponential { .. } | Averaging::PeakHold => 1,
+        };
+        (segments * design.hop()) as Real / design.sample_rate
+    }
+
+    pub fn process(&mut self, input: &[T]) {
+        // The analyzer completes at most one segment per hop of input, so
+        // feeding it a hop at a time catches every estimate.
+        for chunk in input.chunks(self.design().hop()) {
+            if self.analyzer.process(chunk) {
+                self.rows.push(self.analyzer.dbfs().collect());
+            }
+        }
+    }
+
+    /// Discard all rows and buffered input.
+    pub fn clear(&mut self) {
+        self.analyzer.reset();
+        self.rows.clear();
+    }
+
+    /// Draw the rows as an image, one pixel per bin and row, with the lowest
+    /// frequency on the left and the oldest row at the top.
+    pub fn render(&self, style: &Render) -> Image {
+        assert!(style.max_db > style.min_db);
+        let num_bins = self.analyzer.num_bins();
+        let num_rows = self.rows.len();
+
+        let freqs: Vec<Real> = self.analyzer.freqs().collect();
+        let first_freq = freqs.first().copied().unwrap_or(0.0);
+        let last_freq = freqs.last().copied().unwrap_or(0.0);
+        let row_interval = self.row_interval();
+        let duration = num_rows as Real * row_interval;
+
+        let text_height = GLYPH_HEIGHT * TEXT_SCALE;
+        let time_ticks = if style.axes {
+            ticks(0.0, duration, num_rows / (4 * text_height), "s")
+        } else {
+            Vec::new()
+        };
+        let left = if style.axes {
+            let widest = time_ticks
+                .iter()
+                .map(|(_, label)| Image::text_width(label, TEXT_SCALE))
+                .max()
+                .unwrap_or(0);
+            widest + TICK_LENGTH + 3
+        } else {
+            0
+        };
+        let bottom = if style.axes {
+            TICK_LENGTH + text_height + 4
+        } else {
+            0
+        };
+
+        let mut image = Image::new(left + num_bins, num_rows + bottom, BLACK);
+        let range = style.max_db - style.min_db;
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, &level) in row.iter().enumerate() {
+                let color = style.colormap.color((level - style.min_db) / range);
+                image.set(left + x, y, color);
+            }
+        }
+
+        if !style.axes {
+            return image;
+        }
+
+        for (time, label) in time_ticks {
+            let y = (time / row_interval).round() as usize;
+            if y >= num_rows {
+                continue;
+            }
+            image.fill_rect(left - TICK_LENGTH, y, TICK_LENGTH, 1, WHITE);
+            let width = Image::text_width(&label, TEXT_SCALE);
+            let top = y
+                .saturating_sub(text_height / 2)
+                .min(image.height().saturating_sub(bottom + text_height));
+            image.draw_text(
+                left - TICK_LENGTH - 2 - width,
+                top,
+                &label,
+                TEXT_SCALE,
+                WHITE,
+            );
+        }
+
+        let label_width = Image::text_width("-000.0kHz", TEXT_SCALE);
+        let max_freq_ticks = num_bins / (label_width + 4 * TEXT_SCALE);
+        let resolution = self.design().resolution();
+        for (freq, label) in ticks(first_freq, last_freq, max_freq_ticks, "Hz") {
+            let x = left + ((freq - first_freq) / resolution).round() as usize;
+            image.fill_rect(x, num_rows, 1, TICK_LENGTH, WHITE);
+            let width = Image::text_width(&label, TEXT_SCALE);
+            let start = x
+                .saturating_sub(width / 2)
+                .clamp(left, image.width().saturating_sub(width).max(left));
+            image.draw_text(start, num_rows + TICK_LENGTH + 2, &label, TEXT_SCALE, WHITE);
+        }
+
+        image
+    }
+}
+
+/// Evenly spaced values between `start` and `end`, at most `max_ticks` of
+/// them, at a round step of 1, 2 or 5 times a power of ten, along with their
+/// labels.
+fn ticks(start: Real, end: Real, max_ticks: usize, unit: &str) -> Vec<(Real, String)> {
+    let span = end - start;
+    if max_ticks == 0 || span <= 0.0 {
+        return Vec::new();
+    }
+
+    let rough = span / max_ticks as Real;
+    let magnitude = (10.0 as Real).powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|mantissa| mantissa * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(10.0 * magnitude);
+
+    // Scale the labels to whole units where the step allows.
+    let (divisor, prefix) = if step >= 1e6 {
+        (1e6, "M")
+    } else if step >= 1e3 {
+        (1e3, "k")
+    } else if step < 1e-3 {
+        (1e-6, "u")
+    } else if step < 1.0 && unit == "s" {
+        (1e-3, "m")
+    } else {
+        (1.0, "")
+    };
+    let decimals = (-(step / divisor).log10().floor()).max(0.0) as usize;
+
+    let first = (start / step).ceil() as i64;
+    let last = (end / step).floor() as i64;
+    (first..=last)
+        .map(|index| {
+            let value = index as Real * step;
+            let label = format!("{:.*}{prefix}{unit}", decimals, value / divisor);
+            (value, label)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{filter::Window, math::TAU};
+
+    #[test]
+    fn colormap_endpoints() {
+        for (colormap, low, high) in [
+            (Colormap::Grayscale, [0, 0, 0], [255, 255, 255]),
+            (Colormap::Viridis, [68, 1, 84], [253, 231, 37]),
+            (Colormap::Inferno, [0, 0, 4], [252, 255, 164]),
+            (Colormap::Jet, [0, 0, 128], [128, 0, 0]),
+        ] {
+            assert_eq!(colormap.color(0.0), low);
+            assert_eq!(colormap.color(1.0), high);
+            assert_eq!(colormap.color(-3.0), low);
+            assert_eq!(colormap.color(Real::NEG_INFINITY), low);
+            assert_eq!(colormap.color(Real::NAN), low);
+            assert_eq!(colormap.color(7.0), high);
+            assert_eq!(colormap.color(Real::INFINITY), high);
+        }
+        assert_eq!(Colormap::Grayscale.color(0.5), [128, 128, 128]);
+    }
+
+    #[test]
+    fn tick_labels() {
+        let labels = |start, end, max_ticks, unit| -> Vec<String> {
+            ticks(start, end, max_ticks, unit)
+                .into_iter()
+                .map(|(_, label)| label)
+                .collect()
+        };
+        assert_eq!(
+            labels(0.0, 10.0, 5, "s"),
+            ["0s", "2s", "4s", "6s", "8s", "10s"]
+        );
+        assert_eq!(labels(0.0, 0.035, 4, "s"), ["0ms", "10ms", "20ms", "30ms"]);
+        assert_eq!(
+            labels(-24000.0, 24000.0, 4, "Hz"),
+            ["-20kHz", "0kHz", "20kHz"]
+        );
+        assert_eq!(
+            labels(300.0, 2700.0, 5, "Hz"),
+            ["500Hz", "1000Hz", "1500Hz", "2000Hz", "2500Hz"]
+        );
+        assert_eq!(labels(0.0, 1.2, 3, "Hz"), ["0.0Hz", "0.5Hz", "1.0Hz"]);
+        assert_eq!(labels(1e6, 3e6, 3, "Hz"), ["1MHz", "2MHz", "3MHz"]);
+        assert!(labels(0.0, 1.0, 0, "s").is_empty());
+        assert!(labels(1.0, 1.0, 5, "s").is_empty());
+    }
+
+    #[test]
+    fn render_tone() {
+        let design = Welch {
+            size: 64,
+            window: Window::HANN,
+            overlap: 0.5,
+            averaging: Averaging::Exponential {
+                time_constant: 0.01,
+            },
+            sample_rate: 8000.0,
+        };
+        let mut waterfall: Waterfall = Waterfall::new(design);
+        // On bin 16.
+        let tone: Vec<Real> = (0..64 * 40)
+            .map(|n| (TAU * (n % 4) as Real / 4.0).sin())
+            .collect();
+        waterfall.process(&tone);
+        let num_rows = waterfall.rows().len();
+        assert_eq!(num_rows, 79);
+
+        let style = Render {
+            colormap: Colormap::Grayscale,
+            min_db: -100.0,
+            max_db: 20.0,
+            axes: false,
+        };
+        let image = waterfall.render(&style);
+        assert_eq!((image.width(), image.height()), (33, num_rows));
+        for y in 0..num_rows {
+            // The full-scale tone at 0 dBFS, five sixths of the way up.
+            let level = image.get(16, y)[0];
+            assert!((212..=213).contains(&level), "row {y}: {level}");
+            // Far from the tone, below -80 dBFS.
+            assert!(image.get(4, y)[0] < 43, "row {y}");
+        }
+
+        // Axes add margins on the left and below.
+        let image = waterfall.render(&Render {
+            axes: true,
+            ..style
+        });
+        assert!(image.width() > 33 && image.height() > num_rows);
+
+        waterfall.clear();
+        assert!(waterfall.rows().is_empty());
+    }
+}
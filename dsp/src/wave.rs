@@ -1,12 +1,22 @@
+use std::sync::OnceLock;
+
 use crate::{
     iq::IQ,
-    math::{Real, TAU},
+    math::{fast_sincos, Real, TAU},
 };
 
+/// A source of carrier samples whose phase can be nudged, for mixers and
+/// phase-locked loops such as [`Costas`](crate::pll::Costas).
+pub trait LocalOscillator {
+    /// Generate the next sample, shifted by `phase_offset_angle` radians.
+    fn next_with_offset(&mut self, phase_offset_angle: Real) -> IQ;
+}
+
 /// Local oscillator outputting IQ samples.
 pub struct Oscillator {
     phase: Real,
     period: Real,
+    fast_trig: bool,
 }
 
 impl Oscillator {
@@ -20,16 +30,28 @@ impl Oscillator {
     /// ahead of 1:
     ///
     /// ```
+    /// use k9api_dsp::wave::Oscillator;
+    ///
     /// let osc = Oscillator::new(16.0, 4.0);
     /// ````
-    pub fn new(period: f32, starting_phase: f32) -> Self {
+    pub fn new(period: Real, starting_phase: Real) -> Self {
         Self {
             period,
             phase: starting_phase,
+            fast_trig: false,
         }
     }
 
-    /// Generate the next sample of this iscukkatir.
+    /// Like [`new`](Self::new), but generating samples with
+    /// [`fast_sincos`] instead of the standard library's `sin` and `cos`.
+    pub fn fast(period: Real, starting_phase: Real) -> Self {
+        Self {
+            fast_trig: true,
+            ..Self::new(period, starting_phase)
+        }
+    }
+
+    /// Generate the next sample of this oscillator.
     ///
     /// This automatically increments the internal state; the next call to
     /// `next()` will produce the next sample in succession.
@@ -38,7 +60,13 @@ impl Oscillator {
     }
 
     pub fn next_with_offset(&mut self, phase_offset_angle: Real) -> IQ {
-        let sample = IQ::new_polar(self.phase_angle() + phase_offset_angle, 1.0);
+        let angle = self.phase_angle() + phase_offset_angle;
+        let sample = if self.fast_trig {
+            let (sin, cos) = fast_sincos(angle);
+            IQ::new(cos, sin)
+        } else {
+            IQ::new_polar(angle, 1.0)
+        };
         self.phase = (self.phase + 1.0) % self.period;
         sample
     }
@@ -88,6 +116,121 @@ impl Oscillator {
     }
 }
 
+impl LocalOscillator for Oscillator {
+    fn next_with_offset(&mut self, phase_offset_angle: Real) -> IQ {
+        Oscillator::next_with_offset(self, phase_offset_angle)
+    }
+}
+
+/// Number of bits of phase used to index [`sine_table`].
+const TABLE_BITS: u32 = 10;
+
+/// One cycle of a sine wave in `2^TABLE_BITS` steps, plus the first sample
+/// again at the end so that interpolation doesn't have to wrap around.
+fn sine_table() -> &'static [Real] {
+    static TABLE: OnceLock<Box<[Real]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let len = 1 << TABLE_BITS;
+        (0..=len)
+            .map(|i| (std::f64::consts::TAU * i as f64 / len as f64).sin() as Real)
+            .collect()
+    })
+}
+
+/// Convert a phase in cycles to the fixed-point representation of [`Nco`].
+fn phase_to_fixed(cycles: f64) -> u32 {
+    (cycles.rem_euclid(1.0) * 2.0f64.powi(32)) as u64 as u32
+}
+
+/// Numerically controlled oscillator with a fixed-point phase accumulator and
+/// a sine lookup table.
+///
+/// The phase is a 32-bit fraction of a cycle that wraps around on its own, so
+/// unlike [`Oscillator`] it never accumulates rounding error: the frequency is
+/// exact to within `2^-32` cycles per sample for as long as it runs. Samples
+/// are linearly interpolated from a shared table of 1024 points per cycle,
+/// with a maximum error of about `5e-6` in each component.
+#[derive(Debug, Clone)]
+pub struct Nco {
+    phase: u32,
+    step: u32,
+    table: &'static [Real],
+}
+
+impl Nco {
+    /// Construct an oscillator at `freq` cycles per sample (which may be
+    /// negative) and a starting phase of zero.
+    pub fn new(freq: Real) -> Self {
+        Self {
+            phase: 0,
+            step: phase_to_fixed(freq as f64),
+            table: sine_table(),
+        }
+    }
+
+    /// Frequency in cycles per sample, between -0.5 and 0.5.
+    pub fn freq(&self) -> Real {
+        (self.step as i32 as f64 / 2.0f64.powi(32)) as Real
+    }
+
+    /// Change the frequency, keeping the phase continuous.
+    pub fn set_freq(&mut self, freq: Real) {
+        self.step = phase_to_fixed(freq as f64);
+    }
+
+    /// The phase of the next sample, as a fraction of a cycle between 0 and 1.
+    pub fn phase(&self) -> Real {
+        (self.phase as f64 / 2.0f64.powi(32)) as Real
+    }
+
+    /// Modify the phase of the next sample, as a fraction of a cycle.
+    pub fn set_phase(&mut self, phase: Real) {
+        self.phase = phase_to_fixed(phase as f64);
+    }
+
+    fn sample_at(&self, phase: u32) -> IQ {
+        const QUARTER: u32 = 1 << 30;
+        IQ::new(
+            self.interpolate(phase.wrapping_add(QUARTER)),
+            self.interpolate(phase),
+        )
+    }
+
+    fn interpolate(&self, phase: u32) -> Real {
+        let index = (phase >> (32 - TABLE_BITS)) as usize;
+        let fraction = (phase << TABLE_BITS) as Real * (1.0 / 2.0f64.powi(32)) as Real;
+        let (a, b) = (self.table[index], self.table[index + 1]);
+        a + (b - a) * fraction
+    }
+
+    /// Fill the provided buffer with the next samples of this oscillator.
+    pub fn fill(&mut self, buffer: &mut [IQ]) {
+        for (slot, sample) in buffer.iter_mut().zip(self) {
+            *slot = sample;
+        }
+    }
+}
+
+/// Endless stream of samples.
+impl Iterator for Nco {
+    type Item = IQ;
+
+    fn next(&mut self) -> Option<IQ> {
+        let sample = self.sample_at(self.phase);
+        self.phase = self.phase.wrapping_add(self.step);
+        Some(sample)
+    }
+}
+
+impl LocalOscillator for Nco {
+    fn next_with_offset(&mut self, phase_offset_angle: Real) -> IQ {
+        let offset = phase_to_fixed(phase_offset_angle as f64 / std::f64::consts::TAU);
+        let sample = self.sample_at(self.phase.wrapping_add(offset));
+        self.phase = self.phase.wrapping_add(self.step);
+        sample
+    }
+}
+
 /// Sine wave generator.
 pub struct Sine {
     osc: Oscillator,
@@ -104,9 +247,11 @@ impl Sine {
     /// ahead of zero:
     ///
     /// ```
+    /// use k9api_dsp::wave::Sine;
+    ///
     /// let sine = Sine::new(16.0, 4.0);
     /// ````
-    pub fn new(period: f32, starting_phase: f32) -> Self {
+    pub fn new(period: Real, starting_phase: Real) -> Self {
         Self {
             osc: Oscillator::new(period, starting_phase),
         }
@@ -131,3 +276,104 @@ impl Sine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::Sample;
+
+    /// Error of the generated samples, from rounding the phase angle.
+    #[cfg(not(feature = "f64"))]
+    const SAMPLE_TOLERANCE: Real = 1e-5;
+    #[cfg(feature = "f64")]
+    const SAMPLE_TOLERANCE: Real = 1e-9;
+
+    #[test]
+    fn oscillator_phase_does_not_drift() {
+        let steps = 10_000_000u64;
+        for period in [16.0, 7.3, 100.0 / 3.0, 1234.567] {
+            let mut osc = Oscillator::new(period, 0.0);
+            for _ in 0..steps {
+                osc.next();
+            }
+            // The phase is kept below `period`, where adding one sample is
+            // (nearly) exact, so it doesn't accumulate rounding error.
+            let period = period as f64;
+            let exact = steps as f64 % period;
+            let mut drift = osc.phase() as f64 - exact;
+            drift -= (drift / period).round() * period;
+            assert!(
+                drift.abs() < 1e-6,
+                "period {period}: drifted {drift} samples"
+            );
+
+            let cycles = exact / period;
+            let expected = IQ::new_polar((std::f64::consts::TAU * cycles) as Real, 1.0);
+            let error = (osc.next() - expected).magnitude();
+            assert!(error < SAMPLE_TOLERANCE, "period {period}: error {error}");
+        }
+    }
+
+    /// Documented error of the table lookup, from linear interpolation
+    /// between 1024 points per cycle.
+    const NCO_TOLERANCE: Real = 5e-6;
+
+    fn nco_error(sample: IQ, phase: u32) -> Real {
+        let angle = std::f64::consts::TAU * phase as f64 / 2.0f64.powi(32);
+        let i = (sample.i as f64 - angle.cos()).abs();
+        let q = (sample.q as f64 - angle.sin()).abs();
+        i.max(q) as Real
+    }
+
+    #[test]
+    fn nco_table_error_bound() {
+        let nco = Nco::new(0.0);
+        // Each table interval at its start, interior points and end, up to
+        // the last phase before wrapping around to zero.
+        let interval = 1u32 << (32 - TABLE_BITS);
+        let mut max_error: Real = 0.0;
+        for index in 0..1u32 << TABLE_BITS {
+            for step in 0..16 {
+                let phase = index * interval + step * (interval / 16);
+                max_error = max_error.max(nco_error(nco.sample_at(phase), phase));
+            }
+            let phase = index * interval + (interval - 1);
+            max_error = max_error.max(nco_error(nco.sample_at(phase), phase));
+        }
+        assert!(max_error < NCO_TOLERANCE, "{max_error}");
+        // Interpolating at the middle of an interval is close to the bound.
+        assert!(max_error > NCO_TOLERANCE / 2.0, "{max_error}");
+    }
+
+    #[test]
+    fn nco_wraps_phase() {
+        for freq in [0.1234, -0.3, 0.499] {
+            let mut nco = Nco::new(freq);
+            nco.set_phase(0.99);
+            let step = phase_to_fixed(freq as f64);
+            let mut phase = phase_to_fixed(0.99f32 as f64);
+            for n in 0..10000 {
+                let error = nco_error(nco.next().unwrap(), phase);
+                assert!(error < NCO_TOLERANCE, "{freq}, {n}: {error}");
+                phase = phase.wrapping_add(step);
+            }
+            // The reported phase is the accumulator, however many times it
+            // has wrapped.
+            let expected = phase as f64 / 2.0f64.powi(32);
+            let mut error = nco.phase() as f64 - expected;
+            error -= error.round();
+            assert!(error.abs() < 1e-6, "{freq}: phase off by {error}");
+        }
+    }
+
+    #[test]
+    fn sine_starts_at_phase() {
+        let mut sine = Sine::new(16.0, 4.0);
+        let mut buffer = [0.0; 16];
+        sine.fill(&mut buffer);
+        for (n, &sample) in buffer.iter().enumerate() {
+            let expected = (TAU * (n as Real + 4.0) / 16.0).sin();
+            assert!((sample - expected).abs() < 8.0 * Real::EPSILON, "{n}");
+        }
+    }
+}
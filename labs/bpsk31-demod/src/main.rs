@@ -6,9 +6,14 @@ use k9api_dsp::{
     iq::IQ,
     math::Real,
     pll::Costas,
-    resample::Downsample,
+    resample::{farrow::Farrow, Downsample},
     sample::Sample,
+    spectrum::{
+        waterfall::{Colormap, Render, Waterfall},
+        Averaging, Welch,
+    },
 };
+use std::{fs::File, io::BufWriter};
 
 fn main() {
     let wav_file = WavReader::open("bpsk31.wav").expect("cannot open `bpsk31.wav`");
@@ -31,17 +36,29 @@ fn main() {
     )
     .expect("cannot create `baseband.wav`");
 
+    // WAV sample rates are integers, so resample from the symbol rate.
+    let symbols_rate = 32.0;
+    let mut symbols_resample = Farrow::new(symbol_rate, symbols_rate);
+    let mut resampled_symbols = Vec::new();
     let mut symbols = WavWriter::create(
         "symbols.wav",
         WavSpec {
             channels: 2,
-            sample_rate: 31, // 31.25 :(
+            sample_rate: symbols_rate as u32,
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         },
     )
     .expect("cannot create `symbols.wav`");
 
+    let mut waterfall = Waterfall::new(Welch {
+        size: 1024,
+        window: Window::HANN,
+        overlap: 0.5,
+        averaging: Averaging::Linear { count: 1 },
+        sample_rate: sample_rate as Real,
+    });
+
     let mut input_samples = wav_file.into_samples().peekable();
     let mut pll_input = vec![0.0; decimation_factor];
 
@@ -62,6 +79,7 @@ fn main() {
         pll_input.fill_with(|| {
             input_samples.next().transpose().unwrap().unwrap_or(0) as Real / i16::MAX as Real
         });
+        waterfall.process(&pll_input);
 
         let bb = af_domain.process_af(&mut pll_input);
 
@@ -69,12 +87,11 @@ fn main() {
         baseband.write_sample((bb.q * 32767.0) as i16).unwrap();
 
         if let Some(bit_sample) = timing.process(matched_filter.process_sample(bb)) {
-            symbols
-                .write_sample((bit_sample.i * 32767.0) as i16)
-                .unwrap();
-            symbols
-                .write_sample((bit_sample.q * 32767.0) as i16)
-                .unwrap();
+            symbols_resample.process(&[bit_sample], &mut resampled_symbols);
+            for symbol in resampled_symbols.drain(..) {
+                symbols.write_sample((symbol.i * 32767.0) as i16).unwrap();
+                symbols.write_sample((symbol.q * 32767.0) as i16).unwrap();
+            }
 
             // TODO may need phase correction. Right now it seems to be in phase
             let bit = !differential.process(bit_sample.i > 0.0);
@@ -89,6 +106,15 @@ fn main() {
     symbols.flush().unwrap();
     symbols.finalize().unwrap();
 
+    let image = waterfall.render(&Render {
+        colormap: Colormap::Viridis,
+        min_db: -100.0,
+        max_db: 0.0,
+        axes: true,
+    });
+    let png = File::create("waterfall.png").expect("cannot create `waterfall.png`");
+    image.write_png(BufWriter::new(png)).unwrap();
+
     println!("{:?}", output);
 }
 
@@ -106,6 +132,7 @@ impl AFDomain {
             sample_rate: sample_rate as Real,
             passband: Passband::centered_band_pass(carrier_freq, 100.0),
             transition_width: Some(50.0),
+            attenuation: None,
             num_taps: None,
             window: Window::HAMMING,
         };
@@ -118,6 +145,7 @@ impl AFDomain {
                 cutoff: 0.5 * sample_rate as Real / decimation_factor as Real,
             },
             transition_width: Some(100.0),
+            attenuation: None,
             num_taps: None,
             window: Window::HAMMING,
         };
@@ -133,6 +161,7 @@ impl AFDomain {
             sample_rate: sample_rate as Real,
             passband: Passband::LowPass { cutoff: 50.0 },
             transition_width: Some(50.0),
+            attenuation: None,
             num_taps: None,
             window: Window::HAMMING,
         };
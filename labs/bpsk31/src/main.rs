@@ -17,7 +17,7 @@ fn main() {
     let premod_factor = 16;
     let premod_sample_rate = sample_rate / premod_factor;
     let symbol_rate = 31.25;
-    let sps = premod_sample_rate as f32 / symbol_rate;
+    let sps = premod_sample_rate as Real / symbol_rate;
     assert_eq!(premod_sample_rate, 500);
     assert_eq!(sps, 16.0);
 
@@ -45,6 +45,7 @@ fn main() {
             cutoff: 0.5 * premod_sample_rate as Real,
         },
         transition_width: None,
+        attenuation: None,
         num_taps: Some(65),
         window: Window::HAMMING,
     };
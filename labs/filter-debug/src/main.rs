@@ -15,6 +15,7 @@ fn main() {
         sample_rate: sample_rate as Real,
         passband: Passband::LowPass { cutoff },
         transition_width: Some(transition_width),
+        attenuation: None,
         num_taps: None,
         window: Window::HAMMING,
     };
@@ -31,6 +32,15 @@ fn main() {
         ..low_pass
     };
 
+    for (name, design) in [
+        ("low-pass", &low_pass),
+        ("high-pass", &high_pass),
+        ("band-pass", &band_pass),
+        ("band-reject", &band_reject),
+    ] {
+        to_csv_file(&format!("filter-debug-{name}.csv"), design);
+    }
+
     let mut low_pass = low_pass.build();
     let mut high_pass = high_pass.build();
     let mut band_pass = band_pass.build();
@@ -53,6 +63,18 @@ fn main() {
     to_wav_file(sample_rate as u32, generate_samples);
 }
 
+fn to_csv_file(path: &str, design: &WindowMethod) {
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    let file =
+        BufWriter::new(File::create(path).unwrap_or_else(|_| panic!("cannot create `{path}`")));
+    design
+        .response(1001)
+        .write_csv(file)
+        .expect("failed to write csv");
+}
+
 fn to_wav_file(sample_rate: u32, mut generator: impl FnMut(&mut [Real])) {
     use hound::{WavSpec, WavWriter};
     use std::fs::File;
@@ -1,7 +1,9 @@
 use cpal::traits::*;
 use cpal::SampleRate;
+use k9api_dsp::filter::{Passband, Window, WindowMethod};
 use k9api_dsp::math::PI;
 use k9api_dsp::modem::fm::FmDemod;
+use k9api_dsp::resample::Rational;
 use k9api_dsp::{iq::IQ, math::Real};
 use num_complex::Complex;
 use soapysdr::Device;
@@ -14,6 +16,7 @@ fn main() -> anyhow::Result<()> {
     let dev = Device::new("type=rtlsdr")?;
 
     let sample_rate = 250000;
+    let audio_rate = 48000;
 
     let mut in_stream = dev.rx_stream::<Complex<Real>>(&[0])?;
     let mtu = in_stream.mtu()?;
@@ -26,7 +29,7 @@ fn main() -> anyhow::Result<()> {
         .expect("no default output device");
     let mut output_configs: Vec<_> = adev
         .supported_output_configs()?
-        .filter_map(|cfg| cfg.try_with_sample_rate(SampleRate(sample_rate)))
+        .filter_map(|cfg| cfg.try_with_sample_rate(SampleRate(audio_rate)))
         .filter(|cfg| cfg.channels() == 1)
         .collect();
 
@@ -40,33 +43,48 @@ fn main() -> anyhow::Result<()> {
     let output_config = output_configs.first().unwrap().config();
 
     let mut complex_buffer: Vec<Complex<Real>> = vec![Default::default(); mtu];
-    let mut complex_buffer_position = 0;
-    let mut fm = FmDemod::new();
+    let mut fm = FmDemod::fast();
+
+    // 250 kHz * 24 / 125 = 48 kHz
+    let (interpolation, decimation) = (24, 125);
+    let audio_filter = WindowMethod {
+        gain: 1.0,
+        sample_rate: (sample_rate * interpolation) as Real,
+        passband: Passband::LowPass { cutoff: 15000.0 },
+        transition_width: Some(5000.0),
+        attenuation: None,
+        num_taps: None,
+        window: Window::HAMMING,
+    };
+    let mut resample = Rational::new(
+        interpolation as usize,
+        decimation as usize,
+        audio_filter.build(),
+    );
+    let mut demodulated = Vec::with_capacity(mtu);
+    let mut audio_buffer = Vec::new();
+    let mut audio_buffer_position = 0;
 
     in_stream.activate(None)?;
 
     let mut generator = move |buffer: &mut [Real]| {
-        let mut rem = buffer;
-        while rem.len() > 0 {
-            if complex_buffer_position == 0 {
+        for slot in buffer {
+            while audio_buffer_position == audio_buffer.len() {
                 let read = in_stream
-                    .read(
-                        &mut [&mut complex_buffer[complex_buffer_position..]],
-                        1000000,
-                    )
+                    .read(&mut [&mut complex_buffer[..]], 1000000)
                     .unwrap();
-                complex_buffer_position += read;
-            }
-            for (i, o) in complex_buffer[..complex_buffer_position]
-                .iter()
-                .zip(rem.iter_mut())
-            {
-                *o = fm.next(IQ::from(*i)) / PI;
+                demodulated.clear();
+                demodulated.extend(
+                    complex_buffer[..read]
+                        .iter()
+                        .map(|&sample| fm.next(IQ::from(sample)) / PI),
+                );
+                audio_buffer.clear();
+                audio_buffer_position = 0;
+                resample.process(&demodulated, &mut audio_buffer);
             }
-            let copied = complex_buffer_position.min(rem.len());
-            complex_buffer.copy_within(copied..complex_buffer_position, 0);
-            complex_buffer_position -= copied;
-            rem = &mut rem[copied..];
+            *slot = audio_buffer[audio_buffer_position];
+            audio_buffer_position += 1;
         }
     };
 